@@ -1,9 +1,46 @@
 use glow::HasContext;  
 use crate::render::camera::Camera;
-use crate::render::immediate_render::ImmediateRender;
+use crate::render::immediate_render::{ImmediateRender, ImmediateRenderMesh, ImmediateRenderVertexFormat};
+use crate::render::dynamic_buffer::BufferStreaming;
+use crate::render::render_state::RenderState;
+use crate::render::particle_render::ParticleRender;
+use crate::render::render_stats::RenderStats;
+use crate::render::frustum::Frustum;
 use crate::render::grid_render::*;
+use crate::render::debug_render::*;
+use crate::render::text_render::*;
+use crate::render::bitmap_font;
 use crate::top_down_camera::*;
-use nalgebra::{Isometry3, Point3, Point4, Vector3};
+use crate::input::{Key, KeyboardState};
+use crate::gui::Gui;
+#[cfg(feature = "benchmark")]
+use crate::streaming_benchmark::StreamingBenchmark;
+use nalgebra::{Matrix4, Point2, Point3, Point4, Vector2, Vector3};
+
+// simulation parameters, tweaked at runtime with the gui
+struct ParticleSettings
+{
+    count: usize,
+    gravity: f32,
+    max_launch_speed: f32,
+    spread: f32,            // max sideways speed
+    alpha: f32,
+    paused: bool
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self
+    {
+        ParticleSettings {
+            count: 100000,
+            gravity: -9.8,
+            max_launch_speed: 18.0,
+            spread: 2.0,
+            alpha: 0.6,
+            paused: false
+        }
+    }
+}
 
 struct SimpleParticle
 {
@@ -14,72 +51,141 @@ struct SimpleParticle
 }
 
 impl SimpleParticle {
-    fn new() -> Self
+    fn new(settings: &ParticleSettings) -> Self
     {
         let position = Point3::new(0.0,0.0,0.0);
         let velocity = Vector3::new(
-            rand::random_range(-2.0..2.0),
-            rand::random_range(1.0..18.0),
-            rand::random_range(-2.0..2.0)
+            rand::random_range(-settings.spread..=settings.spread),
+            rand::random_range(1.0..=settings.max_launch_speed.max(1.0)),
+            rand::random_range(-settings.spread..=settings.spread)
         );
         let colour = Point4::new(
             rand::random_range(0.0..1.0),
             rand::random_range(0.0..1.0),
             rand::random_range(0.0..1.0),
-            1.0
+            settings.alpha
         );
         let size = rand::random_range(0.05..0.2);
         Self { position, size, velocity, colour }
     }
 
-    fn tick(particles: &mut Vec<SimpleParticle>, delta_time: f64, im_render: &mut ImmediateRender, inverse_view: Isometry3<f32>)
+    // particles outside the frustum are still simulated, just not drawn
+    fn tick(particles: &mut Vec<SimpleParticle>, settings: &ParticleSettings, delta_time: f64, particle_render: &mut ParticleRender, frustum: &Frustum)
     {
-        const GRAVITY: f64 = -9.8;
-        let camera_up: Vector3<f32> = inverse_view * Vector3::y();      // worldspace up/right for billboarding
-        let camera_right: Vector3<f32> = inverse_view * Vector3::x();
+        particles.resize_with(settings.count, || SimpleParticle::new(settings));
+        let delta_time = if settings.paused { 0.0 } else { delta_time };
         for particle in particles {
-            particle.velocity.y = particle.velocity.y + (GRAVITY * delta_time) as f32;
+            particle.velocity.y = particle.velocity.y + (settings.gravity as f64 * delta_time) as f32;
             particle.position = particle.position + particle.velocity * delta_time as f32;
             particle.size = 0.05 + particle.position.y * 0.02;
             if particle.position.y < 0.0 {
-                *particle = SimpleParticle::new();
+                *particle = SimpleParticle::new(settings);
+            }
+            particle.colour.w = settings.alpha;
+            if frustum.intersects_sphere(&particle.position, particle.size)
+            {
+                particle_render.add_particle(&particle.position, particle.size, &particle.colour);
             }
-
-            let p0 = particle.position + camera_up * particle.size;
-            let p1 = particle.position + (camera_right * -particle.size) + (camera_up * -particle.size);
-            let p2 = particle.position + (camera_right * particle.size) + (camera_up * -particle.size);
-            im_render.add_triangle(&p0, &particle.colour, &p1, &particle.colour, &p2, &particle.colour);
         }
     }
 }
 
+// how clicking in the world moves the top-down camera
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum CameraMoveMode
+{
+    EdgeMove,       // hold the mouse away from the screen centre to move in that direction
+    ClickToMove     // click on the ground to glide over to it
+}
+
 pub struct ApplicationState {
     pub im_render_3d: ImmediateRender,
     pub im_render_2d: ImmediateRender,
+    particle_render: ParticleRender,
+    grid_mesh: ImmediateRenderMesh,
     particles: Vec<SimpleParticle>,
+    particle_settings: ParticleSettings,
+    gui: Gui,
     camera: TopDownCamera,
-    render_camera_3d:Camera
+    camera_move_mode: CameraMoveMode,
+    render_camera_3d:Camera,
+    previous_keyboard_state: KeyboardState,     // to detect key presses
+    previous_left_btn_down: bool,               // to detect clicks
+    click_pending: bool,                        // left button went down in the world, it is a click if released before a touch gesture starts
+    previous_right_btn_down: bool,              // to detect drags
+    previous_mouse_x: i32,
+    pub render_stats: RenderStats,              // totals for the last draw_gl
+    #[cfg_attr(not(feature = "sdl2"), allow(dead_code))]     // only released by the desktop cleanup
+    font_texture: Option<crate::gl_utils::gl_types::Texture>,   // owned here, shared with the immediate renders that draw text
+    #[cfg(feature = "benchmark")]
+    streaming_benchmark: StreamingBenchmark
 }
 
 // main init fn called once on start
 pub fn init(gl : &glow::Context) -> ApplicationState
 {
+    let particle_settings = ParticleSettings::default();
     let mut particles = Vec::new();
-    for _ in 1..4000 {
-        particles.push( SimpleParticle::new() );
+    for _ in 0..particle_settings.count {
+        particles.push( SimpleParticle::new(&particle_settings) );
     }
 
     let cam_start_pos = Point3::new(0.0, 25.0, -10.0);
     let cam_look_target = cam_start_pos + Vector3::new(0.0, -20.0, 5.0);
 
+    let mut im_render_3d = ImmediateRender::new(gl, ImmediateRenderVertexFormat::Compact, BufferStreaming::Ring(3), 1024 * 32, 1024 * 32);
+
+    // the grid never changes, bake it once instead of adding it every frame
+    // thick lines stay readable on high dpi screens, anti-aliasing needs alpha blending
+    im_render_3d.set_render_state(RenderState::alpha_blended());
+    im_render_3d.set_thick_line_antialiasing(true);
+    draw_thick_grid_xz(&mut im_render_3d, 
+        &Point3::new(-64.0, 0.0, -64.0), 
+        &Point3::new(128.0,0.0,128.0), 
+        2.0, 
+        1.5,
+        &Point4::new(0.7,0.7,0.7,1.0));
+    let grid_mesh = im_render_3d.bake_mesh(gl);
+    im_render_3d.set_render_state(RenderState::default());
+
+    let mut im_render_2d = ImmediateRender::new(gl, ImmediateRenderVertexFormat::Full, BufferStreaming::Single, 1024 * 4, 1024 * 4);
+    im_render_2d.set_render_state(RenderState::overlay_2d());
+    im_render_2d.set_ordered_drawing(true);     // no depth testing, so draw order is layer order
+    // the app owns the font texture, hand it to every immediate render that draws text
+    let font_texture = match bitmap_font::create_font_texture(gl) {
+        Ok(texture) => Some(texture),
+        Err(text) => {
+            console_log!("Failed to create font texture - {text}");
+            None
+        }
+    };
+    im_render_2d.set_font_texture(font_texture);
+
+    let mut particle_render = ParticleRender::new(gl, BufferStreaming::Ring(3), particles.len() as u32);
+    particle_render.set_depth_sorting(true);    // the particle cloud overlaps itself from most angles
+
     ApplicationState {
-        im_render_3d: ImmediateRender::new(gl, 1024 * 32),
-        im_render_2d: ImmediateRender::new(gl, 1024 * 4),
+        im_render_3d,
+        im_render_2d,
+        particle_render,
+        grid_mesh,
         particles: particles,
+        particle_settings,
+        gui: Gui::new(),
         camera: TopDownCamera::new(
             cam_start_pos, 
             cam_look_target - cam_start_pos),
-        render_camera_3d: Camera::make_projection(0.1, 100.0, 1.0, 90.0)
+        camera_move_mode: CameraMoveMode::EdgeMove,
+        render_camera_3d: Camera::make_projection(0.1, 100.0, 1.0, 90.0),
+        previous_keyboard_state: KeyboardState::default(),
+        previous_left_btn_down: false,
+        click_pending: false,
+        previous_right_btn_down: false,
+        previous_mouse_x: 0,
+        render_stats: RenderStats::default(),
+        font_texture,
+        #[cfg(feature = "benchmark")]
+        streaming_benchmark: StreamingBenchmark::new(gl)
     }
 }
 
@@ -88,13 +194,98 @@ pub fn tick(state: &mut ApplicationState, input: &crate::input::InputState, delt
 {
     state.im_render_3d.clear();
     state.im_render_2d.clear();
+    state.particle_render.clear();
+
+    // F1 toggles wireframe to check mesh density
+    if input.keyboard_state.was_key_pressed(&state.previous_keyboard_state, Key::F1)
+    {
+        let wireframe = !state.im_render_3d.get_wireframe();
+        state.im_render_3d.set_wireframe(wireframe);
+    }
+    state.previous_keyboard_state = input.keyboard_state.clone();
+
+    // settings panel, goes first so we know if the gui has the mouse before moving the camera
+    state.gui.begin_frame(input);
+    if state.gui.begin_panel(&mut state.im_render_2d, "Particles", &Point2::new(8.0, 40.0), 240.0)
+    {
+        let settings = &mut state.particle_settings;
+        let im_render_2d = &mut state.im_render_2d;
+        let mut count = settings.count as f32;
+        if state.gui.slider(im_render_2d, "count", &mut count, 1000.0, 100000.0)
+        {
+            settings.count = count as usize;
+        }
+        state.gui.slider(im_render_2d, "gravity", &mut settings.gravity, -30.0, 0.0);
+        state.gui.slider(im_render_2d, "launch speed", &mut settings.max_launch_speed, 1.0, 30.0);
+        state.gui.slider(im_render_2d, "spread", &mut settings.spread, 0.0, 8.0);
+        state.gui.slider(im_render_2d, "alpha", &mut settings.alpha, 0.05, 1.0);
+        state.gui.checkbox(im_render_2d, "paused", &mut settings.paused);
+        let mut wireframe = state.im_render_3d.get_wireframe();
+        if state.gui.checkbox(im_render_2d, "wireframe (F1)", &mut wireframe)
+        {
+            state.im_render_3d.set_wireframe(wireframe);
+        }
+        if state.gui.button(im_render_2d, "reset")
+        {
+            *settings = ParticleSettings::default();
+            state.particles.clear();
+        }
+    }
+    state.gui.end_panel(&mut state.im_render_2d);
+    if state.gui.begin_panel(&mut state.im_render_2d, "Camera", &Point2::new(viewport_width as f32 - 168.0, 40.0), 160.0)
+    {
+        let mut click_to_move = state.camera_move_mode == CameraMoveMode::ClickToMove;
+        if state.gui.checkbox(&mut state.im_render_2d, "click to move", &mut click_to_move)
+        {
+            state.camera_move_mode = if click_to_move { CameraMoveMode::ClickToMove } else { CameraMoveMode::EdgeMove };
+        }
+    }
+    state.gui.end_panel(&mut state.im_render_2d);
+    state.gui.end_frame();
 
-    // for particle billboards
-    let inverse_view = state.render_camera_3d.get_view_transform().inverse();
-    SimpleParticle::tick(&mut state.particles, delta_time, &mut state.im_render_3d, inverse_view);
+    // clicks happen on release, so the first finger of a pinch doesn't count as one
+    // the gui has already dropped its capture by the time the button is released, so check it on the press
+    let left_btn_pressed = input.mouse_state.left_btn_down && !state.previous_left_btn_down;
+    let left_btn_released = !input.mouse_state.left_btn_down && state.previous_left_btn_down;
+    if left_btn_pressed
+    {
+        state.click_pending = !state.gui.wants_mouse();
+    }
+    if input.mouse_state.touch_gesture
+    {
+        state.click_pending = false;
+    }
+    let left_btn_clicked = left_btn_released && state.click_pending;
+    if left_btn_released
+    {
+        state.click_pending = false;
+    }
+    let right_btn_dragged = input.mouse_state.right_btn_down && state.previous_right_btn_down;
+    let mouse_move_x = input.mouse_state.position_x - state.previous_mouse_x;
+    state.previous_left_btn_down = input.mouse_state.left_btn_down;
+    state.previous_right_btn_down = input.mouse_state.right_btn_down;
+    state.previous_mouse_x = input.mouse_state.position_x;
 
-    // top-down camera input update
-    if input.mouse_state.left_btn_down 
+    // top-down camera input update, unless the mouse is busy with the gui
+    if state.camera_move_mode == CameraMoveMode::ClickToMove
+    {
+        // pick the ground with last frame's camera, it is what the user was looking at when they clicked
+        if left_btn_clicked
+        {
+            const CAM_CLICK_MOVE_SPEED: f32 = 28.0;     // world space units/s
+            let ray = state.render_camera_3d.screen_to_world_ray(
+                input.mouse_state.position_x as f32, 
+                input.mouse_state.position_y as f32, 
+                viewport_width as f32, 
+                viewport_height as f32);
+            if let Some(ground_point) = ray.intersect_horizontal_plane(0.0)
+            {
+                state.camera.move_speed_multi = CAM_CLICK_MOVE_SPEED;
+                state.camera.set_ground_focus_target(ground_point);
+            }
+        }
+    }
+    else if input.mouse_state.left_btn_down && !state.gui.wants_mouse()
     {
         let mouse_x = input.mouse_state.position_x as f32;
         let mouse_y = input.mouse_state.position_y as f32;
@@ -107,7 +298,7 @@ pub fn tick(state: &mut ApplicationState, input: &crate::input::InputState, delt
         const CAM_MOVE_SPEED_MOUSE_MUL: f32 = 0.1;  // speed = mouse dist to center * this
         const CAM_MAX_MOVE_SPEED: f32 = 28.0;       // world space units/s
         let screen_edge_distance = viewport_width.min(viewport_height) as f32 * 0.5;    // distance to edge of screen
-        let screen_center_to_mouse = Vector3::new(mouse_delta_x, 0.0, mouse_delta_z);
+        let screen_center_to_mouse = state.camera.get_yaw_rotation() * Vector3::new(mouse_delta_x, 0.0, mouse_delta_z);    // keep it screen relative when the camera is turned
         let cam_move_magnitude = (screen_center_to_mouse.magnitude() - screen_edge_distance * MIN_SCREEN_DISTANCE).max(0.0);  
         if cam_move_magnitude > 0.0  
         {
@@ -116,41 +307,106 @@ pub fn tick(state: &mut ApplicationState, input: &crate::input::InputState, delt
 
             // scale camera move speed as a proportion of distance of mouse to center of window
             state.camera.move_speed_multi = (cam_move_magnitude * CAM_MOVE_SPEED_MOUSE_MUL).min(CAM_MAX_MOVE_SPEED);
-            state.camera.set_target(state.camera.current_position + cam_move_target);
+            // keep the target height, so a zoom that is still easing in isn't cancelled
+            let mut new_target = state.camera.current_position + cam_move_target;
+            new_target.y = state.camera.get_target().y;
+            state.camera.set_target(new_target);
         }
     }
+
+    // yaw, right-drag or hold Q/E to orbit around the point we are looking at
+    const CAM_DRAG_YAW_PER_PIXEL: f32 = 0.01;      // radians
+    const CAM_KEY_YAW_SPEED: f32 = 1.5;            // radians/s
+    if right_btn_dragged && !state.gui.wants_mouse()
+    {
+        state.camera.rotate_yaw(-(mouse_move_x as f32) * CAM_DRAG_YAW_PER_PIXEL);
+    }
+    if input.keyboard_state.is_key_down(Key::Q)
+    {
+        state.camera.rotate_yaw(CAM_KEY_YAW_SPEED * delta_time as f32);
+    }
+    if input.keyboard_state.is_key_down(Key::E)
+    {
+        state.camera.rotate_yaw(-CAM_KEY_YAW_SPEED * delta_time as f32);
+    }
+
+    // wheel / pinch zoom, steps get bigger the higher we are so it feels the same at any height
+    if input.mouse_state.wheel_delta != 0.0 && !state.gui.wants_mouse() && !state.gui.is_mouse_over()
+    {
+        const CAM_ZOOM_STEP: f32 = 0.15;        // proportion of the current height per wheel notch
+        const CAM_ZOOM_SPEED: f32 = 28.0;       // world space units/s
+        state.camera.move_speed_multi = state.camera.move_speed_multi.max(CAM_ZOOM_SPEED);
+        state.camera.zoom(input.mouse_state.wheel_delta * state.camera.current_position.y * CAM_ZOOM_STEP);
+    }
     state.camera.tick(delta_time);
 
     // update render camera
     let aspect: f32 = viewport_width as f32 / viewport_height as f32;
     state.render_camera_3d = Camera::make_projection(0.1, 100.0, aspect, 90.0);
     state.camera.apply_to_render_camera(&mut state.render_camera_3d);
+    let frustum = state.render_camera_3d.get_frustum();
 
-    draw_grid_xz(&mut state.im_render_3d, 
-        &Point3::new(-64.0, 0.0, -64.0), 
-        &Point3::new(128.0,0.0,128.0), 
-        2.0, 
-        &Point4::new(0.7,0.7,0.7,1.0));
+    SimpleParticle::tick(&mut state.particles, &state.particle_settings, delta_time, &mut state.particle_render, &frustum);
+
+    draw_axes(&mut state.im_render_3d, &Matrix4::identity(), 4.0, 12, DebugShapeStyle::Solid);
+
+    // label the particle emitter and some grid coordinates
+    let viewport_size = Vector2::new(viewport_width as f32, viewport_height as f32);
+    let label_colour = Point4::new(1.0, 1.0, 0.6, 1.0);
+    draw_world_text(&mut state.im_render_2d, &state.render_camera_3d, &viewport_size, &Point3::origin(), "emitter", 1.0, &label_colour);
+    const GRID_LABEL_STEP: usize = 16;
+    for z in (-64..=64).step_by(GRID_LABEL_STEP)
+    {
+        for x in (-64..=64).step_by(GRID_LABEL_STEP)
+        {
+            // skip whole label cells outside the view, draw_world_text culls the rest
+            // testing the cell rather than the label point keeps labels just off the edge, their text can still be on screen
+            let position = Point3::new(x as f32, 0.0, z as f32);
+            let cell_half_size = Vector3::new(GRID_LABEL_STEP as f32 * 0.5, 0.0, GRID_LABEL_STEP as f32 * 0.5);
+            if (x != 0 || z != 0) && frustum.intersects_aabb(&(position - cell_half_size), &(position + cell_half_size))
+            {
+                let label = format!("{x},{z}");
+                draw_world_text(&mut state.im_render_2d, &state.render_camera_3d, &viewport_size, &position, &label, 1.0, &label_colour);
+            }
+        }
+    }
+
+    // frame time + stats from the last draw, the shadow keeps it readable over anything
+    let stats_text = format!("{:.2}ms\n{}", delta_time * 1000.0, state.render_stats);
+    draw_text(&mut state.im_render_2d, &Point2::new(9.0, 9.0), &stats_text, 1.0, &Point4::new(0.0, 0.0, 0.0, 0.8));
+    draw_text(&mut state.im_render_2d, &Point2::new(8.0, 8.0), &stats_text, 1.0, &Point4::new(1.0, 1.0, 1.0, 1.0));
+
+    #[cfg(feature = "benchmark")]
+    state.streaming_benchmark.tick(delta_time);
 }
 
 // main update/drawing entry point
-pub fn draw_gl(gl : &glow::Context, state: &ApplicationState,viewport_width: u32, viewport_height: u32)
+pub fn draw_gl(gl : &glow::Context, state: &mut ApplicationState,viewport_width: u32, viewport_height: u32)
 {
     unsafe {
         gl.viewport(0, 0, viewport_width as i32, viewport_height as i32);
         gl.clear_color(0.3, 0.3, 0.35, 1.0);
         gl.clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
-        gl.enable(glow::DEPTH_TEST);
-        gl.depth_func(glow::LESS);
+        gl.depth_func(glow::LESS);     // depth test on/off is part of each immediate render batch
     }
-    state.im_render_3d.draw(gl, &state.render_camera_3d);
+    let viewport_size = Vector2::new(viewport_width as f32, viewport_height as f32);
+
+    // grid goes first, it is furthest away and blends with the clear colour
+    state.im_render_3d.draw_mesh(gl, &state.grid_mesh, &state.render_camera_3d, &Matrix4::identity(), &viewport_size);
+    state.im_render_3d.draw(gl, &state.render_camera_3d, &viewport_size);
+    state.particle_render.draw(gl, &state.render_camera_3d);      // translucent, so after the opaque stuff
+
+    #[cfg(feature = "benchmark")]
+    state.streaming_benchmark.draw(gl, &state.render_camera_3d, &viewport_size);
 
     // 2d stuff always uses ortho projection matching viewport size
     let render_camera_2d = Camera::make_orthographic(0.0, viewport_width as f32, viewport_height as f32, 0.0, -1.0, 1.0);
-    unsafe {
-        gl.disable(glow::DEPTH_TEST);
-    }
-    state.im_render_2d.draw(gl, &render_camera_2d);
+    state.im_render_2d.draw(gl, &render_camera_2d, &viewport_size);
+
+    let mut render_stats = state.im_render_3d.get_render_stats();
+    render_stats += state.particle_render.get_render_stats();
+    render_stats += state.im_render_2d.get_render_stats();
+    state.render_stats = render_stats;
 }
 
 // cleanup function for desktop app
@@ -159,4 +415,13 @@ pub fn cleanup_gl_resources(gl : &glow::Context, state: &mut ApplicationState)
 {
     state.im_render_3d.cleanup(gl);
     state.im_render_2d.cleanup(gl);
+    state.grid_mesh.cleanup(gl);
+    state.particle_render.cleanup(gl);
+    if let Some(font_texture) = state.font_texture.take()
+    {
+        crate::gl_utils::unload_texture(gl, &font_texture);
+    }
+
+    #[cfg(feature = "benchmark")]
+    state.streaming_benchmark.cleanup(gl);
 }
\ No newline at end of file
@@ -62,4 +62,37 @@ pub fn unload_shader_program(gl : &glow::Context, program: &gl_types::ShaderProg
     unsafe {
         gl.delete_program(*program);
     }
+}
+
+// create a 2d texture from tightly packed rgba8 pixels, no mipmaps
+// filter should be glow::LINEAR or glow::NEAREST
+pub fn create_texture_rgba8(gl : &glow::Context, width: u32, height: u32, pixels: &[u8], filter: u32) -> Result<gl_types::Texture, String>
+{
+    if pixels.len() != (width * height * 4) as usize
+    {
+        return Err(format!("Expected {} bytes of pixel data, got {}", width * height * 4, pixels.len()));
+    }
+
+    let texture: gl_types::Texture;
+    unsafe {
+        texture = gl.create_texture()?;
+        gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+        gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
+        gl.tex_image_2d(glow::TEXTURE_2D, 0, glow::RGBA8 as i32, width as i32, height as i32, 0, glow::RGBA, glow::UNSIGNED_BYTE, glow::PixelUnpackData::Slice(Some(pixels)));
+
+        // no mipmaps, clamp so non-power-of-2 textures work everywhere
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, filter as i32);
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, filter as i32);
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE as i32);
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE as i32);
+        gl.bind_texture(glow::TEXTURE_2D, None);
+    }
+    Ok(texture)
+}
+
+pub fn unload_texture(gl : &glow::Context, texture: &gl_types::Texture)
+{
+    unsafe {
+        gl.delete_texture(*texture);
+    }
 }
\ No newline at end of file
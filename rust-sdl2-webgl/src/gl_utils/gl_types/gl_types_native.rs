@@ -2,4 +2,5 @@
 
 pub type ShaderProgram = glow::NativeProgram;
 pub type VertexArray = glow::NativeVertexArray;
-pub type Buffer = glow::NativeBuffer;
\ No newline at end of file
+pub type Buffer = glow::NativeBuffer;
+pub type Texture = glow::NativeTexture;
\ No newline at end of file
@@ -2,4 +2,5 @@
 
 pub type ShaderProgram = glow::WebProgramKey;
 pub type VertexArray = glow::VertexArray;
-pub type Buffer = glow::Buffer;
\ No newline at end of file
+pub type Buffer = glow::Buffer;
+pub type Texture = glow::Texture;
\ No newline at end of file
@@ -0,0 +1,296 @@
+// Small immediate-mode gui drawn through a 2d ImmediateRender
+// widgets are declared every tick between begin_frame/end_frame and laid out top to bottom inside panels
+// positions are in pixels from the top left of the viewport, the same space as the 2d orthographic camera
+// the 2d immediate render should use ordered drawing so widgets layer correctly
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use nalgebra::{Point2, Point3, Point4, Vector2};
+use crate::input::InputState;
+use crate::render::immediate_render::ImmediateRender;
+use crate::render::text_render::{draw_text, measure_text};
+
+const ROW_HEIGHT: f32 = 20.0;
+const PADDING: f32 = 4.0;
+const TEXT_SCALE: f32 = 1.0;
+const CHECKBOX_SIZE: f32 = 12.0;
+
+const PANEL_COLOUR: Point4<f32> = Point4::new(0.1, 0.1, 0.12, 0.85);
+const HEADER_COLOUR: Point4<f32> = Point4::new(0.2, 0.25, 0.4, 0.95);
+const WIDGET_COLOUR: Point4<f32> = Point4::new(0.25, 0.25, 0.3, 1.0);
+const WIDGET_HOT_COLOUR: Point4<f32> = Point4::new(0.35, 0.35, 0.45, 1.0);
+const WIDGET_ACTIVE_COLOUR: Point4<f32> = Point4::new(0.45, 0.5, 0.7, 1.0);
+const TEXT_COLOUR: Point4<f32> = Point4::new(1.0, 1.0, 1.0, 1.0);
+
+// the panel currently being filled with widgets
+struct GuiPanel
+{
+    id: u64,
+    position: Point2<f32>,
+    width: f32,
+    cursor_y: f32       // top of the next widget
+}
+
+#[derive(Default)]
+pub struct Gui
+{
+    mouse_position: Point2<f32>,
+    mouse_down: bool,
+    mouse_pressed: bool,            // went down this frame
+    mouse_released: bool,           // went up this frame
+    right_mouse_down: bool,         // widgets only use the left button, but a right drag starting on the gui is still captured
+    active_widget: Option<u64>,     // widget the mouse was pressed on, it owns the mouse until release
+    mouse_captured: bool,           // the current left or right press started over the gui
+    panel_rects: Vec<(Point2<f32>, Vector2<f32>)>,      // area covered by each panel this frame
+    previous_panel_rects: Vec<(Point2<f32>, Vector2<f32>)>,
+    open_panels: HashMap<u64, bool>,
+    current_panel: Option<GuiPanel>
+}
+
+fn get_widget_id(parent_id: u64, label: &str) -> u64
+{
+    let mut hasher = DefaultHasher::new();
+    parent_id.hash(&mut hasher);
+    label.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn rect_contains(position: &Point2<f32>, size: &Vector2<f32>, point: &Point2<f32>) -> bool
+{
+    point.x >= position.x && point.y >= position.y && point.x < position.x + size.x && point.y < position.y + size.y
+}
+
+fn draw_rect(im_render: &mut ImmediateRender, position: &Point2<f32>, size: &Vector2<f32>, colour: &Point4<f32>)
+{
+    let v0 = Point3::new(position.x, position.y, 0.0);
+    let v1 = Point3::new(position.x + size.x, position.y, 0.0);
+    let v2 = Point3::new(position.x + size.x, position.y + size.y, 0.0);
+    let v3 = Point3::new(position.x, position.y + size.y, 0.0);
+    im_render.add_triangle(&v0, colour, &v1, colour, &v2, colour);
+    im_render.add_triangle(&v0, colour, &v2, colour, &v3, colour);
+}
+
+// text is vertically centred in a row, left aligned at x
+fn draw_row_text(im_render: &mut ImmediateRender, x: f32, row_y: f32, text: &str)
+{
+    let text_size = measure_text(text, TEXT_SCALE);
+    draw_text(im_render, &Point2::new(x, (row_y + (ROW_HEIGHT - text_size.y) * 0.5).round()), text, TEXT_SCALE, &TEXT_COLOUR);
+}
+
+impl Gui {
+    pub fn new() -> Self
+    {
+        Gui::default()
+    }
+
+    pub fn begin_frame(&mut self, input: &InputState)
+    {
+        let mouse_down = input.mouse_state.left_btn_down;
+        self.mouse_pressed = mouse_down && !self.mouse_down;
+        self.mouse_released = !mouse_down && self.mouse_down;
+        self.mouse_down = mouse_down;
+        let right_mouse_pressed = input.mouse_state.right_btn_down && !self.right_mouse_down;
+        self.right_mouse_down = input.mouse_state.right_btn_down;
+        self.mouse_position = Point2::new(input.mouse_state.position_x as f32, input.mouse_state.position_y as f32);
+
+        // widgets aren't declared yet, so decide who owns a new press using where the panels were last frame
+        // a press of the other button doesn't release a capture that is already held
+        if self.mouse_pressed || right_mouse_pressed
+        {
+            self.mouse_captured |= self.is_mouse_over();
+        }
+        self.panel_rects.clear();
+    }
+
+    pub fn end_frame(&mut self)
+    {
+        if self.current_panel.is_some()
+        {
+            console_log!("Gui::end_frame called inside a panel, missing end_panel");
+            self.current_panel = None;
+        }
+        if !self.mouse_down
+        {
+            self.active_widget = None;
+        }
+        if !self.mouse_down && !self.right_mouse_down
+        {
+            self.mouse_captured = false;
+        }
+        std::mem::swap(&mut self.panel_rects, &mut self.previous_panel_rects);
+    }
+
+    // true while a mouse button is held on the gui, the rest of the app should ignore the mouse
+    pub fn wants_mouse(&self) -> bool
+    {
+        self.mouse_captured || self.active_widget.is_some()
+    }
+
+    // true while the mouse is over any panel, for input that isn't a button press (wheel, hover)
+    // uses the panels from the last end_frame, so after end_frame it is this frame's panels
+    pub fn is_mouse_over(&self) -> bool
+    {
+        self.previous_panel_rects.iter().any(|(position, size)| rect_contains(position, size, &self.mouse_position))
+    }
+
+    // returns true if the panel is open, widgets should only be added if it is
+    // end_panel must be called either way
+    pub fn begin_panel(&mut self, im_render: &mut ImmediateRender, title: &str, position: &Point2<f32>, width: f32) -> bool
+    {
+        let id = get_widget_id(0, title);
+        let header_size = Vector2::new(width, ROW_HEIGHT);
+        let header_hovered = rect_contains(position, &header_size, &self.mouse_position);
+        if header_hovered && self.mouse_pressed
+        {
+            self.active_widget = Some(id);
+        }
+        let open = self.open_panels.entry(id).or_insert(true);
+        if header_hovered && self.mouse_released && self.active_widget == Some(id)
+        {
+            *open = !*open;
+        }
+        let open = *open;
+
+        draw_rect(im_render, position, &header_size, &HEADER_COLOUR);
+        draw_row_text(im_render, position.x + PADDING, position.y, &format!("{} {}", if open { "-" } else { "+" }, title));
+
+        // anything wider than the panel is clipped, the height isn't known yet
+        im_render.push_clip_rect(position, &Vector2::new(width, 1.0e6));
+        self.current_panel = Some(GuiPanel {
+            id,
+            position: *position,
+            width,
+            cursor_y: position.y + ROW_HEIGHT
+        });
+        open
+    }
+
+    pub fn end_panel(&mut self, im_render: &mut ImmediateRender)
+    {
+        match self.current_panel.take() {
+            Some(panel) => {
+                im_render.pop_clip_rect();
+                self.panel_rects.push((panel.position, Vector2::new(panel.width, panel.cursor_y - panel.position.y)));
+            },
+            None => {
+                console_log!("Gui::end_panel called without a matching begin_panel");
+            }
+        }
+    }
+
+    // starts a new row in the current panel, draws the panel background behind it
+    // returns the row position + size, and the widget id
+    fn add_row(&mut self, im_render: &mut ImmediateRender, label: &str) -> Option<(Point2<f32>, Vector2<f32>, u64)>
+    {
+        let Some(panel) = self.current_panel.as_mut() else {
+            console_log!("Gui widget '{label}' added outside of a panel");
+            return None;
+        };
+        let row_position = Point2::new(panel.position.x, panel.cursor_y);
+        let row_size = Vector2::new(panel.width, ROW_HEIGHT + PADDING);
+        panel.cursor_y += row_size.y;
+        let id = get_widget_id(panel.id, label);
+        draw_rect(im_render, &row_position, &row_size, &PANEL_COLOUR);
+
+        // the widget itself sits inside the padding
+        let widget_position = row_position + Vector2::new(PADDING, PADDING);
+        let widget_size = Vector2::new(row_size.x - PADDING * 2.0, ROW_HEIGHT);
+        Some((widget_position, widget_size, id))
+    }
+
+    // press + release tracking shared by the widgets, returns (hovered, clicked)
+    fn update_widget_input(&mut self, id: u64, position: &Point2<f32>, size: &Vector2<f32>) -> (bool, bool)
+    {
+        let hovered = rect_contains(position, size, &self.mouse_position);
+        if hovered && self.mouse_pressed
+        {
+            self.active_widget = Some(id);
+        }
+        let clicked = hovered && self.mouse_released && self.active_widget == Some(id);
+        (hovered, clicked)
+    }
+
+    fn get_widget_colour(&self, id: u64, hovered: bool) -> Point4<f32>
+    {
+        if self.active_widget == Some(id) && self.mouse_down
+        {
+            WIDGET_ACTIVE_COLOUR
+        }
+        else if hovered
+        {
+            WIDGET_HOT_COLOUR
+        }
+        else
+        {
+            WIDGET_COLOUR
+        }
+    }
+
+    pub fn label(&mut self, im_render: &mut ImmediateRender, text: &str)
+    {
+        if let Some((position, _, _)) = self.add_row(im_render, text)
+        {
+            draw_row_text(im_render, position.x, position.y, text);
+        }
+    }
+
+    // returns true on the frame the button is clicked
+    pub fn button(&mut self, im_render: &mut ImmediateRender, text: &str) -> bool
+    {
+        let Some((position, size, id)) = self.add_row(im_render, text) else {
+            return false;
+        };
+        let (hovered, clicked) = self.update_widget_input(id, &position, &size);
+        draw_rect(im_render, &position, &size, &self.get_widget_colour(id, hovered));
+        let text_width = measure_text(text, TEXT_SCALE).x;
+        draw_row_text(im_render, (position.x + (size.x - text_width) * 0.5).round(), position.y, text);
+        clicked
+    }
+
+    // returns true if the value changed this frame
+    pub fn checkbox(&mut self, im_render: &mut ImmediateRender, text: &str, value: &mut bool) -> bool
+    {
+        let Some((position, size, id)) = self.add_row(im_render, text) else {
+            return false;
+        };
+        let (hovered, clicked) = self.update_widget_input(id, &position, &size);
+        if clicked
+        {
+            *value = !*value;
+        }
+
+        let box_position = Point2::new(position.x, position.y + (ROW_HEIGHT - CHECKBOX_SIZE) * 0.5);
+        let box_size = Vector2::new(CHECKBOX_SIZE, CHECKBOX_SIZE);
+        draw_rect(im_render, &box_position, &box_size, &self.get_widget_colour(id, hovered));
+        if *value
+        {
+            draw_rect(im_render, &(box_position + Vector2::new(3.0, 3.0)), &(box_size - Vector2::new(6.0, 6.0)), &TEXT_COLOUR);
+        }
+        draw_row_text(im_render, position.x + CHECKBOX_SIZE + PADDING * 2.0, position.y, text);
+        clicked
+    }
+
+    // drag anywhere on the slider to set the value, returns true if the value changed this frame
+    pub fn slider(&mut self, im_render: &mut ImmediateRender, text: &str, value: &mut f32, min: f32, max: f32) -> bool
+    {
+        let Some((position, size, id)) = self.add_row(im_render, text) else {
+            return false;
+        };
+        let (hovered, _) = self.update_widget_input(id, &position, &size);
+        let mut changed = false;
+        if self.active_widget == Some(id) && self.mouse_down
+        {
+            let t = ((self.mouse_position.x - position.x) / size.x).clamp(0.0, 1.0);
+            let new_value = min + (max - min) * t;
+            changed = new_value != *value;
+            *value = new_value;
+        }
+
+        draw_rect(im_render, &position, &size, &WIDGET_COLOUR);
+        let fill = if max > min { ((*value - min) / (max - min)).clamp(0.0, 1.0) } else { 0.0 };
+        draw_rect(im_render, &position, &Vector2::new(size.x * fill, size.y), &self.get_widget_colour(id, hovered));
+        draw_row_text(im_render, position.x + PADDING, position.y, &format!("{text}: {value:.2}"));
+        changed
+    }
+}
\ No newline at end of file
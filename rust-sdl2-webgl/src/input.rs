@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 #[derive(Default)]
 pub struct MouseState
 {
@@ -5,11 +7,42 @@ pub struct MouseState
     pub position_y: i32,
     pub left_btn_down: bool,
     pub middle_btn_down: bool,
-    pub right_btn_down: bool
+    pub right_btn_down: bool,
+    pub wheel_delta: f32,       // scroll since the last tick in wheel notches, positive = away from the user (zoom in)
+    pub touch_gesture: bool     // a multi-touch gesture is in progress, the left button press it started from is not a click
+}
+
+// platform independent key ids, each context maps its own key codes to these
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Key
+{
+    F1,
+    Q,
+    E
+}
+
+#[derive(Default, Clone)]
+pub struct KeyboardState
+{
+    pub keys_down: HashSet<Key>
+}
+
+impl KeyboardState {
+    pub fn is_key_down(&self, key: Key) -> bool
+    {
+        self.keys_down.contains(&key)
+    }
+
+    // true on the first frame a key is held, compared against the state from the previous tick
+    pub fn was_key_pressed(&self, previous: &KeyboardState, key: Key) -> bool
+    {
+        self.is_key_down(key) && !previous.is_key_down(key)
+    }
 }
 
 #[derive(Default)]
 pub struct InputState
 {
-    pub mouse_state: MouseState
+    pub mouse_state: MouseState,
+    pub keyboard_state: KeyboardState
 }
\ No newline at end of file
@@ -5,6 +5,10 @@ pub mod app;            // make application callbacks public to the crate
 pub mod render;         // make render stuff public
 pub mod input;
 pub mod top_down_camera;
+pub mod gui;
+
+#[cfg(feature = "benchmark")]
+pub mod streaming_benchmark;    // dynamic buffer streaming benchmark scene
 
 // import platform contexts as modules
 #[cfg(feature = "sdl2")]
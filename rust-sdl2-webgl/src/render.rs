@@ -1,3 +1,11 @@
 pub mod camera;
 pub mod immediate_render;
-pub mod grid_render;
\ No newline at end of file
+pub mod dynamic_buffer;
+pub mod render_state;
+pub mod grid_render;
+pub mod debug_render;
+pub mod particle_render;
+pub mod render_stats;
+pub mod bitmap_font;
+pub mod text_render;
+pub mod frustum;
\ No newline at end of file
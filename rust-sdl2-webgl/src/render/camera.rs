@@ -1,4 +1,36 @@
-use nalgebra::{Perspective3, Orthographic3, Isometry3, Point3, Vector3, Matrix4};
+use crate::render::frustum::Frustum;
+use nalgebra::{Perspective3, Orthographic3, Isometry3, Point2, Point3, Vector2, Vector3, Matrix4};
+
+// a half-line in world space, direction is normalized
+#[derive(Clone, Copy, Debug)]
+pub struct Ray
+{
+    pub origin: Point3<f32>,
+    pub direction: Vector3<f32>
+}
+
+impl Ray {
+    pub fn get_point_at(&self, distance: f32) -> Point3<f32>
+    {
+        self.origin + self.direction * distance
+    }
+
+    // where the ray hits the horizontal plane at y = height
+    // None if the ray is parallel to the plane or pointing away from it
+    pub fn intersect_horizontal_plane(&self, height: f32) -> Option<Point3<f32>>
+    {
+        if self.direction.y.abs() < 1.0e-6
+        {
+            return None;
+        }
+        let distance = (height - self.origin.y) / self.direction.y;
+        if distance < 0.0
+        {
+            return None;
+        }
+        Some(self.get_point_at(distance))
+    }
+}
 
 enum ProjectionTransform {
     Perspective { transform: Perspective3<f32> },
@@ -48,6 +80,14 @@ impl Camera {
         }
     }
 
+    pub fn get_projection_matrix(&self) -> Matrix4<f32>
+    {
+        match self.projection_transform {
+            ProjectionTransform::Perspective { transform, .. } => transform.to_homogeneous(),
+            ProjectionTransform::Orthographic { transform, .. } => transform.to_homogeneous()
+        }
+    }
+
     pub fn make_projection(near: f32, far: f32, aspect: f32, fov: f32) -> Self
     {
         let position = Point3::new(0.0, 0.0, 0.0);
@@ -85,4 +125,136 @@ impl Camera {
         self.view_transform
     }
 
+    // planes are in world space, use them to skip things that can't be seen
+    pub fn get_frustum(&self) -> Frustum
+    {
+        Frustum::from_view_projection(&self.get_view_projection_matrix())
+    }
+
+    // ray from the near plane through a pixel, x/y are in pixels from the top left of the viewport
+    // perspective rays start at the near plane and spread out, orthographic rays are parallel
+    pub fn screen_to_world_ray(&self, x: f32, y: f32, viewport_width: f32, viewport_height: f32) -> Ray
+    {
+        // ndc y points up, screen y points down
+        let ndc_x = x / viewport_width * 2.0 - 1.0;
+        let ndc_y = 1.0 - y / viewport_height * 2.0;
+        let (near_view, far_view) = match self.projection_transform {
+            ProjectionTransform::Perspective { transform, .. } => (
+                transform.unproject_point(&Point3::new(ndc_x, ndc_y, -1.0)),
+                transform.unproject_point(&Point3::new(ndc_x, ndc_y, 1.0))
+            ),
+            ProjectionTransform::Orthographic { transform, .. } => (
+                transform.unproject_point(&Point3::new(ndc_x, ndc_y, -1.0)),
+                transform.unproject_point(&Point3::new(ndc_x, ndc_y, 1.0))
+            )
+        };
+        let near_world = self.view_transform.inverse_transform_point(&near_view);
+        let far_world = self.view_transform.inverse_transform_point(&far_view);
+        Ray {
+            origin: near_world,
+            direction: (far_world - near_world).normalize()
+        }
+    }
+
+    // pixel position of a world space point, from the top left of the viewport
+    // None if the point is behind the camera or outside the near/far planes
+    // points to the sides of the view are still returned, so the caller can decide how far off screen is too far
+    pub fn world_to_screen(&self, point: &Point3<f32>, viewport_size: &Vector2<f32>) -> Option<Point2<f32>>
+    {
+        let clip_position = self.get_view_projection_matrix() * point.to_homogeneous();
+        if clip_position.w <= 0.0 || clip_position.z < -clip_position.w || clip_position.z > clip_position.w
+        {
+            return None;
+        }
+        let ndc_position = clip_position.xy() / clip_position.w;
+        Some(Point2::new((ndc_position.x * 0.5 + 0.5) * viewport_size.x, (0.5 - ndc_position.y * 0.5) * viewport_size.y))
+    }
+
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    const VIEWPORT_SIZE: Vector2<f32> = Vector2::new(800.0, 600.0);
+
+    // a perspective and an orthographic camera looking down at the same point, shared with the frustum tests
+    pub(crate) fn make_test_cameras() -> [Camera; 2]
+    {
+        let mut perspective = Camera::make_projection(0.1, 100.0, VIEWPORT_SIZE.x / VIEWPORT_SIZE.y, std::f32::consts::FRAC_PI_2);
+        perspective.look_at(Point3::new(3.0, 10.0, -6.0), Point3::new(1.0, 0.0, 2.0), Vector3::y());
+        let mut orthographic = Camera::make_orthographic(-20.0, 20.0, -15.0, 15.0, 0.1, 100.0);
+        orthographic.look_at(Point3::new(3.0, 10.0, -6.0), Point3::new(1.0, 0.0, 2.0), Vector3::y());
+        [perspective, orthographic]
+    }
+
+    fn get_forward(camera: &Camera) -> Vector3<f32>
+    {
+        (camera.target - camera.position).normalize()
+    }
+
+    fn get_distance_to_ray(ray: &Ray, point: &Point3<f32>) -> f32
+    {
+        let to_point = point - ray.origin;
+        (to_point - ray.direction * to_point.dot(&ray.direction)).magnitude()
+    }
+
+    #[test]
+    fn world_to_screen_round_trips_through_screen_to_world_ray()
+    {
+        for camera in make_test_cameras()
+        {
+            for point in [Point3::new(1.0, 0.0, 2.0), Point3::new(-4.0, 1.0, 5.0), Point3::new(6.0, -2.0, 3.0)]
+            {
+                let pixel = camera.world_to_screen(&point, &VIEWPORT_SIZE).expect("point should be in front of the camera");
+                let ray = camera.screen_to_world_ray(pixel.x, pixel.y, VIEWPORT_SIZE.x, VIEWPORT_SIZE.y);
+                assert!((ray.direction.magnitude() - 1.0).abs() < 1.0e-4);
+                assert!((point - ray.origin).dot(&ray.direction) > 0.0, "point should be ahead of the ray origin");
+                assert!(get_distance_to_ray(&ray, &point) < 1.0e-3, "ray misses {point} by {}", get_distance_to_ray(&ray, &point));
+            }
+        }
+    }
+
+    #[test]
+    fn screen_centre_ray_looks_at_the_target()
+    {
+        for camera in make_test_cameras()
+        {
+            let ray = camera.screen_to_world_ray(VIEWPORT_SIZE.x * 0.5, VIEWPORT_SIZE.y * 0.5, VIEWPORT_SIZE.x, VIEWPORT_SIZE.y);
+            assert!((ray.direction - get_forward(&camera)).magnitude() < 1.0e-4);
+            assert!(get_distance_to_ray(&ray, &camera.target) < 1.0e-3);
+        }
+    }
+
+    #[test]
+    fn orthographic_rays_are_parallel()
+    {
+        let camera = &make_test_cameras()[1];
+        let corner_ray = camera.screen_to_world_ray(0.0, 0.0, VIEWPORT_SIZE.x, VIEWPORT_SIZE.y);
+        assert!((corner_ray.direction - get_forward(camera)).magnitude() < 1.0e-4);
+    }
+
+    #[test]
+    fn world_to_screen_rejects_points_behind_or_outside_near_far()
+    {
+        for camera in make_test_cameras()
+        {
+            let forward = get_forward(&camera);
+            assert!(camera.world_to_screen(&(camera.position - forward * 5.0), &VIEWPORT_SIZE).is_none(), "behind the camera");
+            assert!(camera.world_to_screen(&(camera.position + forward * 0.05), &VIEWPORT_SIZE).is_none(), "closer than the near plane");
+            assert!(camera.world_to_screen(&(camera.position + forward * 150.0), &VIEWPORT_SIZE).is_none(), "beyond the far plane");
+            assert!(camera.world_to_screen(&(camera.position + forward * 50.0), &VIEWPORT_SIZE).is_some());
+        }
+    }
+
+    #[test]
+    fn ray_hits_horizontal_plane()
+    {
+        let ray = Ray { origin: Point3::new(1.0, 10.0, 2.0), direction: Vector3::new(0.0, -1.0, 1.0).normalize() };
+        let hit = ray.intersect_horizontal_plane(0.0).expect("ray points down at the plane");
+        assert!((hit - Point3::new(1.0, 0.0, 12.0)).magnitude() < 1.0e-4);
+        assert!(ray.intersect_horizontal_plane(20.0).is_none(), "plane is behind the ray");
+        let flat_ray = Ray { origin: Point3::new(0.0, 1.0, 0.0), direction: Vector3::x() };
+        assert!(flat_ray.intersect_horizontal_plane(0.0).is_none(), "ray is parallel to the plane");
+    }
 }
\ No newline at end of file
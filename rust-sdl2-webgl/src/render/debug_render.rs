@@ -0,0 +1,221 @@
+// Helpers to draw debug shapes as solid triangles or wireframe lines
+// Round shapes are built around their local Y axis, with 'segments' steps around the circumference
+
+use std::f32::consts::{PI, TAU};
+use nalgebra::{Matrix4, Point3, Point4, Rotation3, Translation3, Vector3};
+use crate::render::immediate_render::ImmediateRender;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DebugShapeStyle
+{
+    Solid,
+    Wireframe
+}
+
+// revolve a profile of (radius, height) points around the local Y axis
+// points with zero radius collapse to a single point (poles, cone tips, cap centres)
+fn draw_revolved_profile(im_render: &mut ImmediateRender, transform: &Matrix4<f32>, profile: &[(f32, f32)], segments: u32, colour: &Point4<f32>, style: DebugShapeStyle)
+{
+    let segments = segments.max(3);
+    let mut first_index = 0;
+    for (ring, (radius, y)) in profile.iter().enumerate()
+    {
+        for segment in 0..segments
+        {
+            let angle = segment as f32 / segments as f32 * TAU;
+            let local_pos = Point3::new(radius * angle.cos(), *y, radius * angle.sin());
+            let index = im_render.add_vertex(&transform.transform_point(&local_pos), colour);
+            if ring == 0 && segment == 0
+            {
+                first_index = index;
+            }
+        }
+    }
+
+    let vertex_index = |ring: usize, segment: u32| first_index + ring as u32 * segments + (segment % segments);
+    for ring in 0..profile.len()
+    {
+        for segment in 0..segments
+        {
+            let a = vertex_index(ring, segment);
+            let b = vertex_index(ring, segment + 1);
+            match style {
+                DebugShapeStyle::Solid => {
+                    if ring + 1 < profile.len()
+                    {
+                        let c = vertex_index(ring + 1, segment + 1);
+                        let d = vertex_index(ring + 1, segment);
+                        im_render.add_indexed_triangle(a, d, c);
+                        im_render.add_indexed_triangle(a, c, b);
+                    }
+                },
+                DebugShapeStyle::Wireframe => {
+                    if profile[ring].0 > 0.0
+                    {
+                        im_render.add_indexed_line(a, b);
+                    }
+                    if ring + 1 < profile.len()
+                    {
+                        im_render.add_indexed_line(a, vertex_index(ring + 1, segment));
+                    }
+                }
+            }
+        }
+    }
+}
+
+// box centered on the transform origin
+pub fn draw_box(im_render: &mut ImmediateRender, transform: &Matrix4<f32>, half_extents: &Vector3<f32>, colour: &Point4<f32>, style: DebugShapeStyle)
+{
+    // corner index bits = x | y << 1 | z << 2, set bit = positive extent
+    let mut first_index = 0;
+    for corner in 0..8
+    {
+        let local_pos = Point3::new(
+            if corner & 1 != 0 { half_extents.x } else { -half_extents.x },
+            if corner & 2 != 0 { half_extents.y } else { -half_extents.y },
+            if corner & 4 != 0 { half_extents.z } else { -half_extents.z }
+        );
+        let index = im_render.add_vertex(&transform.transform_point(&local_pos), colour);
+        if corner == 0
+        {
+            first_index = index;
+        }
+    }
+
+    match style {
+        DebugShapeStyle::Solid => {
+            // counter-clockwise when seen from outside, +x, -x, +y, -y, +z, -z
+            const FACES: [[u32; 4]; 6] = [[1, 3, 7, 5], [4, 6, 2, 0], [7, 3, 2, 6], [1, 5, 4, 0], [5, 7, 6, 4], [0, 2, 3, 1]];
+            for face in FACES
+            {
+                im_render.add_indexed_triangle(first_index + face[0], first_index + face[1], first_index + face[2]);
+                im_render.add_indexed_triangle(first_index + face[0], first_index + face[2], first_index + face[3]);
+            }
+        },
+        DebugShapeStyle::Wireframe => {
+            // an edge joins every pair of corners that differ in one axis
+            for corner in 0..8
+            {
+                for axis_bit in [1, 2, 4]
+                {
+                    if corner & axis_bit == 0
+                    {
+                        im_render.add_indexed_line(first_index + corner, first_index + (corner | axis_bit));
+                    }
+                }
+            }
+        }
+    }
+}
+
+// min/max are in the local space of the transform, pass identity for a world space box
+pub fn draw_aabb(im_render: &mut ImmediateRender, transform: &Matrix4<f32>, min: &Point3<f32>, max: &Point3<f32>, colour: &Point4<f32>, style: DebugShapeStyle)
+{
+    let center = nalgebra::center(min, max);
+    let half_extents = (max - min) * 0.5;
+    draw_box(im_render, &(transform * Translation3::from(center.coords).to_homogeneous()), &half_extents, colour, style);
+}
+
+pub fn draw_sphere(im_render: &mut ImmediateRender, transform: &Matrix4<f32>, radius: f32, segments: u32, colour: &Point4<f32>, style: DebugShapeStyle)
+{
+    let rings = (segments / 2).max(2);
+    let profile: Vec<(f32, f32)> = (0..=rings).map(|ring| {
+        let angle = -PI * 0.5 + PI * ring as f32 / rings as f32;
+        (radius * angle.cos(), radius * angle.sin())
+    }).collect();
+    draw_revolved_profile(im_render, transform, &profile, segments, colour, style);
+}
+
+// circle in the local XZ plane, facing +Y
+pub fn draw_circle(im_render: &mut ImmediateRender, transform: &Matrix4<f32>, radius: f32, segments: u32, colour: &Point4<f32>, style: DebugShapeStyle)
+{
+    let profile = match style {
+        DebugShapeStyle::Solid => vec![(radius, 0.0), (0.0, 0.0)],
+        DebugShapeStyle::Wireframe => vec![(radius, 0.0)]
+    };
+    draw_revolved_profile(im_render, transform, &profile, segments, colour, style);
+}
+
+// cylinder centered on the transform origin
+pub fn draw_cylinder(im_render: &mut ImmediateRender, transform: &Matrix4<f32>, radius: f32, height: f32, segments: u32, colour: &Point4<f32>, style: DebugShapeStyle)
+{
+    let half_height = height * 0.5;
+    let profile = [(0.0, -half_height), (radius, -half_height), (radius, half_height), (0.0, half_height)];
+    draw_revolved_profile(im_render, transform, &profile, segments, colour, style);
+}
+
+// cone base sits on the transform origin, tip points along +Y
+pub fn draw_cone(im_render: &mut ImmediateRender, transform: &Matrix4<f32>, radius: f32, height: f32, segments: u32, colour: &Point4<f32>, style: DebugShapeStyle)
+{
+    let profile = [(0.0, 0.0), (radius, 0.0), (0.0, height)];
+    draw_revolved_profile(im_render, transform, &profile, segments, colour, style);
+}
+
+// capsule centered on the transform origin, height is the distance between the hemisphere centres
+pub fn draw_capsule(im_render: &mut ImmediateRender, transform: &Matrix4<f32>, radius: f32, height: f32, segments: u32, colour: &Point4<f32>, style: DebugShapeStyle)
+{
+    let half_height = height * 0.5;
+    let hemisphere_rings = (segments / 4).max(2);
+    let mut profile = Vec::new();
+    for ring in 0..=hemisphere_rings
+    {
+        let angle = -PI * 0.5 + PI * 0.5 * ring as f32 / hemisphere_rings as f32;
+        profile.push((radius * angle.cos(), radius * angle.sin() - half_height));
+    }
+    for ring in 0..=hemisphere_rings
+    {
+        let angle = PI * 0.5 * ring as f32 / hemisphere_rings as f32;
+        profile.push((radius * angle.cos(), radius * angle.sin() + half_height));
+    }
+    draw_revolved_profile(im_render, transform, &profile, segments, colour, style);
+}
+
+// from/to are in the local space of the transform, wireframe arrows use a line for the shaft
+#[allow(clippy::too_many_arguments)]
+pub fn draw_arrow(im_render: &mut ImmediateRender, transform: &Matrix4<f32>, from: &Point3<f32>, to: &Point3<f32>, head_radius: f32, segments: u32, colour: &Point4<f32>, style: DebugShapeStyle)
+{
+    let direction = to - from;
+    let length = direction.magnitude();
+    if length <= 0.0
+    {
+        return;
+    }
+
+    // rotate local +Y onto the arrow direction, rotation_between fails if they point in opposite directions
+    let rotation = Rotation3::rotation_between(&Vector3::y(), &direction)
+        .unwrap_or_else(|| Rotation3::from_axis_angle(&Vector3::x_axis(), PI));
+    let head_length = (head_radius * 2.5).min(length);
+    let shaft_length = length - head_length;
+    let head_transform = transform * Translation3::from(from.coords + direction * (shaft_length / length)).to_homogeneous() * rotation.to_homogeneous();
+    draw_cone(im_render, &head_transform, head_radius, head_length, segments, colour, style);
+
+    match style {
+        DebugShapeStyle::Solid => {
+            let shaft_transform = transform * Translation3::from(from.coords + direction * (shaft_length * 0.5 / length)).to_homogeneous() * rotation.to_homogeneous();
+            draw_cylinder(im_render, &shaft_transform, head_radius * 0.4, shaft_length, segments, colour, style);
+        },
+        DebugShapeStyle::Wireframe => {
+            let shaft_end = from + direction * (shaft_length / length);
+            im_render.add_line(&transform.transform_point(from), colour, &transform.transform_point(&shaft_end), colour);
+        }
+    }
+}
+
+// x, y, z axes coloured red, green, blue
+pub fn draw_axes(im_render: &mut ImmediateRender, transform: &Matrix4<f32>, length: f32, segments: u32, style: DebugShapeStyle)
+{
+    let axes = [
+        (Vector3::x(), Point4::new(1.0, 0.0, 0.0, 1.0)),
+        (Vector3::y(), Point4::new(0.0, 1.0, 0.0, 1.0)),
+        (Vector3::z(), Point4::new(0.0, 0.0, 1.0, 1.0))
+    ];
+    for (axis, colour) in axes
+    {
+        let end = Point3::from(axis * length);
+        match style {
+            DebugShapeStyle::Solid => draw_arrow(im_render, transform, &Point3::origin(), &end, length * 0.05, segments, &colour, style),
+            DebugShapeStyle::Wireframe => im_render.add_line(&transform.transform_point(&Point3::origin()), &colour, &transform.transform_point(&end), &colour)
+        }
+    }
+}
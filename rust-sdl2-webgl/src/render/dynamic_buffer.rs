@@ -0,0 +1,122 @@
+// A gl buffer that is refilled with new data every frame
+// storage grows automatically if more data is uploaded than it can hold
+
+use glow::HasContext;
+use crate::gl_utils;
+
+// how new data is streamed to the gpu each frame
+// some drivers stall if we write to a buffer the gpu is still reading from the last frame
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BufferStreaming
+{
+    Single,         // one buffer, overwritten every upload
+    Orphan,         // one buffer, but its storage is re-specified before each upload so the driver can hand out fresh memory
+    Ring(u32)       // cycle through n buffers, so a buffer is only written again n uploads later
+}
+
+pub struct DynamicBuffer
+{
+    buffers: Vec<Option<gl_utils::gl_types::Buffer>>,
+    buffer_sizes: Vec<usize>,   // current size of each buffer's gl storage in bytes
+    current_buffer: usize,      // index of the buffer used by the last upload
+    target: u32,                // ARRAY_BUFFER, ELEMENT_ARRAY_BUFFER, etc
+    streaming: BufferStreaming,
+    grow_count: u32             // how many times the storage was reallocated to fit more data
+}
+
+impl DynamicBuffer {
+    pub fn new(gl : &glow::Context, target: u32, initial_size_bytes: usize, streaming: BufferStreaming) -> Self
+    {
+        let buffer_count = match streaming {
+            BufferStreaming::Ring(count) => count.max(1) as usize,
+            _ => 1
+        };
+        let mut buffers = Vec::new();
+        unsafe {
+            for _ in 0..buffer_count
+            {
+                let buffer = match gl.create_buffer() {
+                    Ok(buffer) => Some(buffer),
+                    Err(text) => {
+                        console_log!("Failed to create buffer - {text}");
+                        None
+                    }
+                };
+                gl.bind_buffer(target, buffer);
+                gl.buffer_data_size(target, initial_size_bytes as i32, glow::DYNAMIC_DRAW);
+                buffers.push(buffer);
+            }
+            gl.bind_buffer(target, None);
+        }
+        DynamicBuffer {
+            buffers,
+            buffer_sizes: vec![initial_size_bytes; buffer_count],
+            current_buffer: 0,
+            target,
+            streaming,
+            grow_count: 0
+        }
+    }
+
+    pub fn cleanup(&mut self, gl : &glow::Context)
+    {
+        unsafe {
+            for buffer in &self.buffers
+            {
+                gl.delete_buffer(buffer.unwrap());
+            }
+        }
+        self.buffers.clear();
+        self.buffer_sizes.clear();
+    }
+
+    pub fn get_buffer_count(&self) -> usize
+    {
+        self.buffers.len()
+    }
+
+    pub fn get_buffer_at(&self, index: usize) -> Option<gl_utils::gl_types::Buffer>
+    {
+        self.buffers[index]
+    }
+
+    // index of the buffer that was written by the last upload, for get_buffer_at
+    pub fn get_current_index(&self) -> usize
+    {
+        self.current_buffer
+    }
+
+    pub fn get_grow_count(&self) -> u32
+    {
+        self.grow_count
+    }
+
+    // moves to the next buffer if streaming from a ring, then binds it and copies data to the start of it
+    // reallocates if the data does not fit
+    // note that element array buffers must be uploaded with the owning vao bound
+    pub fn upload(&mut self, gl : &glow::Context, data: &[u8])
+    {
+        if let BufferStreaming::Ring(_) = self.streaming
+        {
+            self.current_buffer = (self.current_buffer + 1) % self.buffers.len();
+        }
+        let current_size = self.buffer_sizes[self.current_buffer];
+        unsafe {
+            gl.bind_buffer(self.target, self.buffers[self.current_buffer]);
+            if data.len() > current_size
+            {
+                // at least double the size so we don't reallocate every frame while geometry grows
+                let new_size_bytes = data.len().max(current_size * 2);
+                console_log!("Growing dynamic buffer from {} to {} bytes", current_size, new_size_bytes);
+                gl.buffer_data_size(self.target, new_size_bytes as i32, glow::DYNAMIC_DRAW);
+                self.buffer_sizes[self.current_buffer] = new_size_bytes;
+                self.grow_count += 1;
+            }
+            else if self.streaming == BufferStreaming::Orphan
+            {
+                gl.buffer_data_size(self.target, current_size as i32, glow::DYNAMIC_DRAW);
+            }
+            gl.buffer_sub_data_u8_slice(self.target, 0, data);
+        }
+    }
+}
\ No newline at end of file
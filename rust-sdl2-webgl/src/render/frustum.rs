@@ -0,0 +1,176 @@
+// View frustum planes for culling, extracted from a view-projection matrix
+// tests are conservative, things near the corners of the frustum can pass when they are outside
+
+use nalgebra::{Matrix4, Point3, Vector3};
+
+// points on the side the normal faces are inside
+#[derive(Clone, Copy, Debug)]
+pub struct Plane
+{
+    pub normal: Vector3<f32>,   // normalized
+    pub distance: f32
+}
+
+impl Plane {
+    // positive in front of the plane, negative behind
+    pub fn get_signed_distance(&self, point: &Point3<f32>) -> f32
+    {
+        self.normal.dot(&point.coords) + self.distance
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum
+{
+    pub planes: [Plane; 6]      // left, right, bottom, top, near, far
+}
+
+impl Frustum {
+    // works for perspective + orthographic, the planes come from the rows of the matrix (Gribb/Hartmann)
+    pub fn from_view_projection(view_projection: &Matrix4<f32>) -> Self
+    {
+        let row = |index: usize| view_projection.row(index).transpose();
+        let make_plane = |coefficients: nalgebra::Vector4<f32>| {
+            let length = coefficients.xyz().magnitude();
+            Plane {
+                normal: coefficients.xyz() / length,
+                distance: coefficients.w / length
+            }
+        };
+        Frustum {
+            planes: [
+                make_plane(row(3) + row(0)),
+                make_plane(row(3) - row(0)),
+                make_plane(row(3) + row(1)),
+                make_plane(row(3) - row(1)),
+                make_plane(row(3) + row(2)),
+                make_plane(row(3) - row(2))
+            ]
+        }
+    }
+
+    pub fn contains_point(&self, point: &Point3<f32>) -> bool
+    {
+        self.planes.iter().all(|plane| plane.get_signed_distance(point) >= 0.0)
+    }
+
+    pub fn intersects_sphere(&self, center: &Point3<f32>, radius: f32) -> bool
+    {
+        self.planes.iter().all(|plane| plane.get_signed_distance(center) >= -radius)
+    }
+
+    pub fn intersects_aabb(&self, min: &Point3<f32>, max: &Point3<f32>) -> bool
+    {
+        // only the corner furthest along each plane normal needs testing
+        self.planes.iter().all(|plane| {
+            let furthest_corner = Point3::new(
+                if plane.normal.x >= 0.0 { max.x } else { min.x },
+                if plane.normal.y >= 0.0 { max.y } else { min.y },
+                if plane.normal.z >= 0.0 { max.z } else { min.z }
+            );
+            plane.get_signed_distance(&furthest_corner) >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::camera::Camera;
+    use crate::render::camera::tests::make_test_cameras;
+
+    const LEFT: usize = 0;
+    const RIGHT: usize = 1;
+    const BOTTOM: usize = 2;
+    const TOP: usize = 3;
+    const NEAR: usize = 4;
+    const FAR: usize = 5;
+
+    // world space position of a point given in normalized device coordinates, -1 to 1 is inside on every axis
+    fn ndc_to_world(camera: &Camera, x: f32, y: f32, z: f32) -> Point3<f32>
+    {
+        camera.get_view_projection_matrix().try_inverse().unwrap().transform_point(&Point3::new(x, y, z))
+    }
+
+    // a point just outside each plane, the index of the plane it is outside of
+    fn get_outside_points(camera: &Camera) -> [(usize, Point3<f32>); 6]
+    {
+        // ndc z past 1 wraps around behind a perspective camera, so go beyond the far plane in world space instead
+        let near_centre = ndc_to_world(camera, 0.0, 0.0, -1.0);
+        let far_centre = ndc_to_world(camera, 0.0, 0.0, 1.0);
+        [
+            (LEFT, ndc_to_world(camera, -1.2, 0.0, 0.5)),
+            (RIGHT, ndc_to_world(camera, 1.2, 0.0, 0.5)),
+            (BOTTOM, ndc_to_world(camera, 0.0, -1.2, 0.5)),
+            (TOP, ndc_to_world(camera, 0.0, 1.2, 0.5)),
+            (NEAR, ndc_to_world(camera, 0.0, 0.0, -1.02)),
+            (FAR, far_centre + (far_centre - near_centre) * 0.1)
+        ]
+    }
+
+    #[test]
+    fn points_inside_every_plane_are_contained()
+    {
+        for camera in make_test_cameras()
+        {
+            let frustum = camera.get_frustum();
+            for inside_point in [ndc_to_world(&camera, 0.0, 0.0, 0.0), ndc_to_world(&camera, 0.9, -0.9, 0.9), ndc_to_world(&camera, -0.9, 0.9, -0.9)]
+            {
+                assert!(frustum.planes.iter().all(|plane| plane.get_signed_distance(&inside_point) > 0.0));
+                assert!(frustum.contains_point(&inside_point));
+                assert!(frustum.intersects_sphere(&inside_point, 0.01));
+                assert!(frustum.intersects_aabb(&(inside_point - Vector3::repeat(0.01)), &(inside_point + Vector3::repeat(0.01))));
+            }
+        }
+    }
+
+    #[test]
+    fn points_outside_each_plane_are_rejected()
+    {
+        for camera in make_test_cameras()
+        {
+            let frustum = camera.get_frustum();
+            for (plane_index, outside_point) in get_outside_points(&camera)
+            {
+                assert!(frustum.planes[plane_index].get_signed_distance(&outside_point) < 0.0, "point should be outside plane {plane_index}");
+                assert!(!frustum.contains_point(&outside_point), "plane {plane_index}");
+            }
+        }
+    }
+
+    #[test]
+    fn spheres_outside_each_plane_are_rejected_unless_they_reach_inside()
+    {
+        for camera in make_test_cameras()
+        {
+            let frustum = camera.get_frustum();
+            let inside_point = ndc_to_world(&camera, 0.0, 0.0, 0.0);
+            for (plane_index, outside_point) in get_outside_points(&camera)
+            {
+                let radius = frustum.planes[plane_index].get_signed_distance(&outside_point).abs() * 0.5;
+                assert!(!frustum.intersects_sphere(&outside_point, radius), "plane {plane_index}");
+                let reaching_radius = (inside_point - outside_point).magnitude();
+                assert!(frustum.intersects_sphere(&outside_point, reaching_radius), "plane {plane_index}");
+            }
+        }
+    }
+
+    #[test]
+    fn aabbs_outside_each_plane_are_rejected_unless_they_reach_inside()
+    {
+        for camera in make_test_cameras()
+        {
+            let frustum = camera.get_frustum();
+            let inside_point = ndc_to_world(&camera, 0.0, 0.0, 0.0);
+            for (plane_index, outside_point) in get_outside_points(&camera)
+            {
+                // small enough to stay on the outside of the plane whichever way the box is rotated relative to it
+                let half_size = Vector3::repeat(frustum.planes[plane_index].get_signed_distance(&outside_point).abs() * 0.25);
+                assert!(!frustum.intersects_aabb(&(outside_point - half_size), &(outside_point + half_size)), "plane {plane_index}");
+                let min = Point3::new(inside_point.x.min(outside_point.x), inside_point.y.min(outside_point.y), inside_point.z.min(outside_point.z));
+                let max = Point3::new(inside_point.x.max(outside_point.x), inside_point.y.max(outside_point.y), inside_point.z.max(outside_point.z));
+                assert!(frustum.intersects_aabb(&min, &max), "plane {plane_index}");
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -1,62 +1,218 @@
 use glow::HasContext;
-use nalgebra::{Point3,Point4};
+use nalgebra::{Isometry3,Matrix4,Point2,Point3,Point4,Vector2};
 use crate::gl_utils;
+use crate::render::dynamic_buffer::{BufferStreaming, DynamicBuffer};
+use crate::render::render_state::{BlendMode, RenderState};
+use crate::render::render_stats::RenderStats;
 
+mod vertex_format;
+pub use vertex_format::ImmediateRenderVertexFormat;
+use vertex_format::VertexAttribute;
+mod retained_mesh;
+pub use retained_mesh::ImmediateRenderMesh;
+
+#[repr(C)]      // uploaded as-is, the field layout must match the attribute offsets in vertex_format
+#[derive(Clone, Copy)]
+pub struct ImmediateRenderVertex
+{
+    position: Point4<f32>,      // w is the point size in pixels for point primitives
+    colour: Point4<f32>,
+    uv: Point2<f32>
+}
+
+impl ImmediateRenderVertex {
+    pub fn new(position: &Point3<f32>, colour: &Point4<f32>) -> Self
+    {
+        Self::new_textured(position, &Point2::new(0.0, 0.0), colour)
+    }
+
+    pub fn new_textured(position: &Point3<f32>, uv: &Point2<f32>, colour: &Point4<f32>) -> Self
+    {
+        ImmediateRenderVertex {
+            position: Point4::new(position.x, position.y, position.z, 1.0),
+            colour: *colour,
+            uv: *uv
+        }
+    }
+
+    pub fn new_point(position: &Point3<f32>, size: f32, colour: &Point4<f32>) -> Self
+    {
+        ImmediateRenderVertex {
+            position: Point4::new(position.x, position.y, position.z, size),
+            colour: *colour,
+            uv: Point2::new(0.0, 0.0)
+        }
+    }
+}
+
+// thick lines are expanded to screen-space quads in the vertex shader
+// each vertex knows both ends of the line so it can work out the screen-space direction
 #[allow(dead_code)]     // Stop compiler warning that we never read these
-struct ImmediateRenderVertex
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ImmediateRenderThickLineVertex
 {
     position: Point4<f32>,
-    colour: Point4<f32>
+    other_position: Point4<f32>,
+    colour: Point4<f32>,
+    params: Point4<f32>     // x = side of the line (-1/1), y = width in pixels, z = anti-alias feather in pixels, w = 0 at line start, 1 at end
+}
+
+const THICK_LINE_ATTRIBUTES: [VertexAttribute; 4] = [
+    VertexAttribute { name: "vs_in_position", components: 4, data_type: glow::FLOAT, normalized: false, offset: 0 },
+    VertexAttribute { name: "vs_in_other_position", components: 4, data_type: glow::FLOAT, normalized: false, offset: 16 },
+    VertexAttribute { name: "vs_in_colour", components: 4, data_type: glow::FLOAT, normalized: false, offset: 32 },
+    VertexAttribute { name: "vs_in_params", components: 4, data_type: glow::FLOAT, normalized: false, offset: 48 }
+];
+
+// scissor rectangle in whole pixels, origin at the top left of the viewport like the 2d orthographic camera
+#[derive(Clone, Copy, PartialEq)]
+struct ImmediateRenderClipRect
+{
+    min_x: i32,
+    min_y: i32,
+    max_x: i32,
+    max_y: i32
 }
 
+impl ImmediateRenderClipRect {
+    fn intersect(&self, other: &ImmediateRenderClipRect) -> ImmediateRenderClipRect
+    {
+        let min_x = self.min_x.max(other.min_x);
+        let min_y = self.min_y.max(other.min_y);
+        ImmediateRenderClipRect {
+            min_x,
+            min_y,
+            max_x: self.max_x.min(other.max_x).max(min_x),     // no overlap = empty rect, not a negative one
+            max_y: self.max_y.min(other.max_y).max(min_y)
+        }
+    }
+}
+
+// everything that must match for two draws to be merged into one batch
+#[derive(Clone, Copy, PartialEq)]
+struct ImmediateRenderBatchState
+{
+    render_state: RenderState,
+    texture: Option<gl_utils::gl_types::Texture>,    // None = untextured
+    round_points: bool,     // only used by point batches
+    clip_rect: Option<ImmediateRenderClipRect>      // None = no scissor
+}
+
+// start/count are vertices for array draws, or indices for indexed draws
+#[derive(Clone, Copy)]
 struct ImmediateRenderDrawcall
 {
-    start_vertex: i32,
-    vertex_count: i32
+    primitive_type: u32,    // glow::TRIANGLES, LINES, etc
+    start: i32,
+    count: i32,
+    indexed: bool,
+    order: u32,             // submission order across all primitive types, for ordered drawing
+    batch_state: ImmediateRenderBatchState
+}
+
+impl ImmediateRenderDrawcall {
+    fn can_merge(&self, next: &ImmediateRenderDrawcall) -> bool
+    {
+        self.primitive_type == next.primitive_type &&
+        self.indexed == next.indexed && 
+        self.start + self.count == next.start && 
+        self.batch_state == next.batch_state
+    }
 }
 
-// immediate render owns a vertex buffer + vertex array
-// vertex buffer is updated each frame with new geometry
+// immediate render owns a vertex buffer + index buffer + vertex array
+// both buffers are updated each frame with new geometry and grow as required
+// if the buffers stream from a ring there is one vertex array per buffer in the ring
+// untextured geometry samples a 1x1 white texture so everything goes through the same shader
+// it also owns the built-in font texture used by text_render
 pub struct ImmediateRender
 {
     shader_program: Option<gl_utils::gl_types::ShaderProgram>,
-    vertex_array: Option<gl_utils::gl_types::VertexArray>,
-    vertex_buffer: Option<gl_utils::gl_types::Buffer>,
+    white_texture: Option<gl_utils::gl_types::Texture>,
+    font_texture: Option<gl_utils::gl_types::Texture>,     // not owned, see set_font_texture
+    vertex_arrays: Vec<Option<gl_utils::gl_types::VertexArray>>,
+    vertex_buffer: DynamicBuffer,
+    index_buffer: DynamicBuffer,
+    vertex_format: ImmediateRenderVertexFormat,
     current_vertices: Vec<ImmediateRenderVertex>,
+    packed_vertices: Vec<u8>,       // scratch space for vertex formats that need packing before upload
+    current_indices: Vec<u32>,
     current_triangle_draws: Vec<ImmediateRenderDrawcall>,
-    current_line_draws: Vec<ImmediateRenderDrawcall>
+    current_line_draws: Vec<ImmediateRenderDrawcall>,
+    current_point_draws: Vec<ImmediateRenderDrawcall>,
+    current_render_state: RenderState,
+    round_points: bool,
+    depth_sorting: bool,
+    transform_stack: Vec<Matrix4<f32>>,     // never empty, the last entry is applied to new geometry
+    clip_rect_stack: Vec<ImmediateRenderClipRect>,  // the last entry clips new geometry, empty = no clipping
+    wireframe: bool,
+    ordered_drawing: bool,
+    frame_stats: RenderStats,   // everything drawn since the last clear
+    viewport_size: Vector2<f32>,    // from the current draw call, for scissor rects and thick line widths
+    thick_line_shader_program: Option<gl_utils::gl_types::ShaderProgram>,
+    thick_line_vertex_arrays: Vec<Option<gl_utils::gl_types::VertexArray>>,
+    thick_line_vertex_buffer: DynamicBuffer,
+    current_thick_line_vertices: Vec<ImmediateRenderThickLineVertex>,
+    current_thick_line_draws: Vec<ImmediateRenderDrawcall>,
+    thick_line_feather: f32
 }
 
 impl ImmediateRender {
     pub fn cleanup(&mut self, gl : &glow::Context)
     {
         unsafe{
-            gl.delete_buffer(self.vertex_buffer.unwrap());
-            gl.delete_vertex_array(self.vertex_array.unwrap());
+            for vertex_array in self.vertex_arrays.iter().chain(self.thick_line_vertex_arrays.iter())
+            {
+                gl.delete_vertex_array(vertex_array.unwrap());
+            }
             gl_utils::unload_shader_program(gl, &self.shader_program.unwrap());
+            gl_utils::unload_shader_program(gl, &self.thick_line_shader_program.unwrap());
+            gl_utils::unload_texture(gl, &self.white_texture.unwrap());
         }
-        self.vertex_buffer = None;
-        self.vertex_array = None;
+        self.vertex_buffer.cleanup(gl);
+        self.index_buffer.cleanup(gl);
+        self.thick_line_vertex_buffer.cleanup(gl);
+        self.vertex_arrays.clear();
+        self.thick_line_vertex_arrays.clear();
         self.shader_program = None;
+        self.thick_line_shader_program = None;
+        self.white_texture = None;
     }
 
-    pub fn new(gl : &glow::Context, max_vertex_count: u32) -> Self {
+    // buffer sizes are only a starting point, they will grow if more geometry is added
+    // the vertex format only changes how vertices are uploaded, the add_* api is the same
+    pub fn new(gl : &glow::Context, vertex_format: ImmediateRenderVertexFormat, streaming: BufferStreaming, initial_vertex_count: u32, initial_index_count: u32) -> Self {
         let vertex_shader_src = r#"#version 300 es
             uniform mat4 view_projection_matrix;
             layout (location = 0) in vec4 vs_in_position; 
             layout (location = 1) in vec4 vs_in_colour;
+            layout (location = 2) in vec2 vs_in_uv;
+            layout (location = 3) in float vs_in_point_size;
             out vec4 vs_out_colour;
+            out vec2 vs_out_uv;
             void main() {
                 gl_Position = view_projection_matrix * vs_in_position;
+                gl_PointSize = vs_in_point_size;
                 vs_out_colour = vs_in_colour;
+                vs_out_uv = vs_in_uv;
             }
         "#;
         let fragment_shader_src = r#"#version 300 es
             precision highp float;
+            uniform sampler2D diffuse_texture;
+            uniform int round_points;
             in vec4 vs_out_colour;
+            in vec2 vs_out_uv;
             out vec4 fs_out_colour;
             void main() {
-                fs_out_colour = vs_out_colour;
+                if (round_points != 0) {
+                    vec2 from_center = gl_PointCoord * 2.0 - 1.0;
+                    if (dot(from_center, from_center) > 1.0) {
+                        discard;
+                    }
+                }
+                fs_out_colour = vs_out_colour * texture(diffuse_texture, vs_out_uv);
             }
         "#;
         let shader_program = match gl_utils::load_shader_program(gl, vertex_shader_src, fragment_shader_src) {
@@ -66,21 +222,56 @@ impl ImmediateRender {
                 None
             }
         };
+        let white_texture = match gl_utils::create_texture_rgba8(gl, 1, 1, &[255, 255, 255, 255], glow::NEAREST) {
+            Ok(texture) => Some(texture),
+            Err(text) => {
+                console_log!("Failed to create white texture - {text}");
+                None
+            }
+        };
+        let vertex_size = vertex_format.get_vertex_size();
+        let vertex_buffer = DynamicBuffer::new(gl, glow::ARRAY_BUFFER, vertex_size * initial_vertex_count as usize, streaming);
+        let index_buffer = DynamicBuffer::new(gl, glow::ELEMENT_ARRAY_BUFFER, size_of::<u32>() * initial_index_count as usize, streaming);
+        let vertex_arrays = (0..vertex_buffer.get_buffer_count()).map(|index| {
+            Self::create_vertex_array(gl, shader_program, vertex_format.get_attributes(), vertex_size, vertex_buffer.get_buffer_at(index), index_buffer.get_buffer_at(index))
+        }).collect();
+        let (thick_line_shader_program, thick_line_vertex_arrays, thick_line_vertex_buffer) = Self::create_thick_line_resources(gl, streaming);
+        Self {
+            vertex_arrays,
+            vertex_buffer: vertex_buffer,
+            index_buffer: index_buffer,
+            shader_program: shader_program,
+            white_texture,
+            font_texture: None,
+            vertex_format,
+            current_vertices: Vec::new(),
+            packed_vertices: Vec::new(),
+            current_indices: Vec::new(),
+            current_triangle_draws: Vec::new(),
+            current_line_draws: Vec::new(),
+            current_point_draws: Vec::new(),
+            current_render_state: RenderState::default(),
+            round_points: false,
+            depth_sorting: false,
+            transform_stack: vec![Matrix4::identity()],
+            clip_rect_stack: Vec::new(),
+            wireframe: false,
+            ordered_drawing: false,
+            frame_stats: RenderStats::default(),
+            viewport_size: Vector2::new(1.0, 1.0),
+            thick_line_shader_program,
+            thick_line_vertex_arrays,
+            thick_line_vertex_buffer,
+            current_thick_line_vertices: Vec::new(),
+            current_thick_line_draws: Vec::new(),
+            thick_line_feather: 0.0
+        }
+    }
+
+    fn create_vertex_array(gl : &glow::Context, shader_program: Option<gl_utils::gl_types::ShaderProgram>, attributes: &[VertexAttribute], vertex_size: usize, 
+        vertex_buffer: Option<gl_utils::gl_types::Buffer>, index_buffer: Option<gl_utils::gl_types::Buffer>) -> Option<gl_utils::gl_types::VertexArray> {
         let vertex_array: Option<gl_utils::gl_types::VertexArray>;
-        let vertex_buffer: Option<gl_utils::gl_types::Buffer>;
-        let vertex_size = size_of::<ImmediateRenderVertex>();
         unsafe {
-            vertex_buffer = match gl.create_buffer() {
-                Ok(buffer) => Some(buffer),
-                Err(text) => {
-                    console_log!("Failed to create vertex buffer - {text}");
-                    None
-                }
-            };
-            let vertex_data_size_bytes = vertex_size * max_vertex_count as usize;
-            gl.bind_buffer(glow::ARRAY_BUFFER, vertex_buffer);
-            gl.buffer_data_size(glow::ARRAY_BUFFER, vertex_data_size_bytes as i32, glow::DYNAMIC_DRAW);
-
             vertex_array = match gl.create_vertex_array() {
                 Ok(vertex_array) => Some(vertex_array),
                 Err(text) => {
@@ -89,120 +280,711 @@ impl ImmediateRender {
                 }
             };
 
-            let position_attrib_location = gl.get_attrib_location(shader_program.unwrap(), "vs_in_position");
-            let colour_attrib_location = gl.get_attrib_location(shader_program.unwrap(), "vs_in_colour");
             gl.bind_vertex_array(vertex_array);
-            gl.enable_vertex_attrib_array(position_attrib_location.unwrap());
-            gl.vertex_attrib_pointer_f32(position_attrib_location.unwrap(), 4, glow::FLOAT, false, vertex_size as i32, 0);
-            let colour_data_offset = size_of::<Point4<f32>>() as i32;
-            gl.enable_vertex_attrib_array(colour_attrib_location.unwrap());
-            gl.vertex_attrib_pointer_f32(colour_attrib_location.unwrap(), 4, glow::FLOAT, false, vertex_size as i32, colour_data_offset );
+            gl.bind_buffer(glow::ARRAY_BUFFER, vertex_buffer);
+            for attribute in attributes
+            {
+                let attrib_location = gl.get_attrib_location(shader_program.unwrap(), attribute.name);
+                gl.enable_vertex_attrib_array(attrib_location.unwrap());
+                gl.vertex_attrib_pointer_f32(attrib_location.unwrap(), attribute.components, attribute.data_type, attribute.normalized, vertex_size as i32, attribute.offset);
+            }
 
-            // reset bound vao/buffer
-            gl.bind_buffer(glow::ARRAY_BUFFER, None);
+            // element buffer binding is part of the vao state
+            gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, index_buffer);
+
+            // reset bound vao/buffer, unbind vao first so it keeps the element buffer
             gl.bind_vertex_array(None);
+            gl.bind_buffer(glow::ARRAY_BUFFER, None);
+            gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, None);
         }
-        Self {
-            vertex_array: vertex_array,
-            vertex_buffer: vertex_buffer,
-            shader_program: shader_program,
-            current_vertices: Vec::new(),
-            current_triangle_draws: Vec::new(),
-            current_line_draws: Vec::new()
+        vertex_array
+    }
+
+    fn create_thick_line_resources(gl : &glow::Context, streaming: BufferStreaming) -> (Option<gl_utils::gl_types::ShaderProgram>, Vec<Option<gl_utils::gl_types::VertexArray>>, DynamicBuffer) {
+        // gl lines are clamped to 1 pixel wide on webgl/gles, so expand each line into a quad in screen space
+        // line ends behind the camera are clipped to the near plane first, otherwise the screen-space direction flips
+        let vertex_shader_src = r#"#version 300 es
+            uniform mat4 view_projection_matrix;
+            uniform vec2 viewport_size;
+            layout (location = 0) in vec4 vs_in_position;
+            layout (location = 1) in vec4 vs_in_other_position;
+            layout (location = 2) in vec4 vs_in_colour;
+            layout (location = 3) in vec4 vs_in_params;
+            out vec4 vs_out_colour;
+            out float vs_out_edge_distance;
+            flat out float vs_out_half_width;
+            flat out float vs_out_feather;
+            vec4 clip_to_near(vec4 clip_pos, vec4 clip_other) {
+                const float min_w = 0.0001;
+                if (clip_pos.w < min_w) {
+                    return mix(clip_other, clip_pos, (clip_other.w - min_w) / (clip_other.w - clip_pos.w));
+                }
+                return clip_pos;
+            }
+            void main() {
+                vec4 clip_this = view_projection_matrix * vec4(vs_in_position.xyz, 1.0);
+                vec4 clip_other = view_projection_matrix * vec4(vs_in_other_position.xyz, 1.0);
+                if (clip_this.w < 0.0001 && clip_other.w < 0.0001) {
+                    gl_Position = vec4(2.0, 2.0, 2.0, 1.0);     // entirely behind the camera, output a degenerate triangle outside the view
+                    return;
+                }
+                vec4 clipped_this = clip_to_near(clip_this, clip_other);
+                vec4 clipped_other = clip_to_near(clip_other, clip_this);
+                vec2 half_viewport = viewport_size * 0.5;
+                vec2 screen_this = (clipped_this.xy / clipped_this.w) * half_viewport;
+                vec2 screen_other = (clipped_other.xy / clipped_other.w) * half_viewport;
+                vec2 screen_direction = (vs_in_params.w > 0.5) ? (screen_this - screen_other) : (screen_other - screen_this);
+                screen_direction = length(screen_direction) > 0.0 ? normalize(screen_direction) : vec2(1.0, 0.0);
+                vec2 screen_normal = vec2(-screen_direction.y, screen_direction.x);
+
+                // the quad includes the feather so anti-aliased edges are not cut off
+                float half_width = vs_in_params.y * 0.5;
+                float quad_half_width = half_width + vs_in_params.z;
+                vec2 offset = screen_normal * vs_in_params.x * quad_half_width;
+                gl_Position = vec4(clipped_this.xy + (offset / half_viewport) * clipped_this.w, clipped_this.zw);
+                vs_out_colour = vs_in_colour;
+                vs_out_edge_distance = vs_in_params.x * quad_half_width;
+                vs_out_half_width = half_width;
+                vs_out_feather = vs_in_params.z;
+            }
+        "#;
+        let fragment_shader_src = r#"#version 300 es
+            precision highp float;
+            in vec4 vs_out_colour;
+            in float vs_out_edge_distance;
+            flat in float vs_out_half_width;
+            flat in float vs_out_feather;
+            out vec4 fs_out_colour;
+            void main() {
+                float coverage = 1.0;
+                if (vs_out_feather > 0.0) {
+                    coverage = clamp((vs_out_half_width + vs_out_feather * 0.5 - abs(vs_out_edge_distance)) / vs_out_feather, 0.0, 1.0);
+                }
+                fs_out_colour = vec4(vs_out_colour.rgb, vs_out_colour.a * coverage);
+            }
+        "#;
+        let shader_program = match gl_utils::load_shader_program(gl, vertex_shader_src, fragment_shader_src) {
+            Ok(shader_program) => Some(shader_program),
+            Err(text) => {
+                console_log!("Failed to load thick line shaders - {text}");
+                None
+            }
+        };
+        let vertex_size = size_of::<ImmediateRenderThickLineVertex>();
+        let vertex_buffer = DynamicBuffer::new(gl, glow::ARRAY_BUFFER, vertex_size * 6 * 1024, streaming);
+        let vertex_arrays = (0..vertex_buffer.get_buffer_count()).map(|index| {
+            Self::create_vertex_array(gl, shader_program, &THICK_LINE_ATTRIBUTES, vertex_size, vertex_buffer.get_buffer_at(index), None)
+        }).collect();
+        (shader_program, vertex_arrays, vertex_buffer)
+    }
+
+    // all geometry goes through here so it picks up the current transform
+    fn push_vertex(&mut self, mut vertex: ImmediateRenderVertex) {
+        let position = self.get_transform().transform_point(&vertex.position.xyz());
+        vertex.position = Point4::new(position.x, position.y, position.z, vertex.position.w);
+        self.current_vertices.push(vertex);
+    }
+
+    // positions passed to add_* calls are transformed by the top of the transform stack before being stored
+    // pushed transforms are combined with the current one, so they are relative to the parent
+    pub fn push_transform(&mut self, transform: impl Into<Matrix4<f32>>) {
+        let combined = self.get_transform() * transform.into();
+        self.transform_stack.push(combined);
+    }
+
+    pub fn pop_transform(&mut self) {
+        if self.transform_stack.len() > 1
+        {
+            self.transform_stack.pop();
+        }
+        else
+        {
+            console_log!("ImmediateRender::pop_transform called without a matching push_transform");
+        }
+    }
+
+    // replaces the current transform without touching the rest of the stack
+    pub fn set_transform(&mut self, transform: impl Into<Matrix4<f32>>) {
+        *self.transform_stack.last_mut().unwrap() = transform.into();
+    }
+
+    pub fn get_transform(&self) -> &Matrix4<f32> {
+        self.transform_stack.last().unwrap()
+    }
+
+    // geometry added until the matching pop_clip_rect is only drawn inside this rect
+    // position + size are in pixels from the top left of the viewport, the same space as the 2d orthographic camera
+    // clip rects are not affected by the transform stack, nested rects are clipped by their parent
+    pub fn push_clip_rect(&mut self, position: &Point2<f32>, size: &Vector2<f32>) {
+        let mut clip_rect = ImmediateRenderClipRect {
+            min_x: position.x.floor() as i32,
+            min_y: position.y.floor() as i32,
+            max_x: (position.x + size.x.max(0.0)).ceil() as i32,
+            max_y: (position.y + size.y.max(0.0)).ceil() as i32
+        };
+        if let Some(parent) = self.clip_rect_stack.last()
+        {
+            clip_rect = parent.intersect(&clip_rect);
+        }
+        self.clip_rect_stack.push(clip_rect);
+    }
+
+    pub fn pop_clip_rect(&mut self) {
+        if self.clip_rect_stack.pop().is_none()
+        {
+            console_log!("ImmediateRender::pop_clip_rect called without a matching push_clip_rect");
         }
     }
 
     pub fn add_triangle(&mut self, v0: &Point3<f32>, c0: &Point4<f32>, v1: &Point3<f32>, c1: &Point4<f32>, v2: &Point3<f32>, c2: &Point4<f32>) {
-        let v0 = ImmediateRenderVertex {
-            position: Point4::new(v0.x, v0.y, v0.z, 1.0),
-            colour: *c0
+        let draw = ImmediateRenderDrawcall {
+            primitive_type: glow::TRIANGLES,
+            start: self.current_vertices.len() as i32,
+            count: 3,
+            indexed: false,
+            order: self.get_next_draw_order(),
+            batch_state: self.get_batch_state(None)
         };
-        let v1 = ImmediateRenderVertex {
-            position: Point4::new(v1.x, v1.y, v1.z, 1.0),
-            colour: *c1
+        self.push_vertex(ImmediateRenderVertex::new(v0, c0));
+        self.push_vertex(ImmediateRenderVertex::new(v1, c1));
+        self.push_vertex(ImmediateRenderVertex::new(v2, c2));
+        self.current_triangle_draws.push(draw);
+    }
+
+    pub fn add_line(&mut self, v0: &Point3<f32>, c0: &Point4<f32>, v1: &Point3<f32>, c1: &Point4<f32>) {
+        let draw = ImmediateRenderDrawcall {
+            primitive_type: glow::LINES,
+            start: self.current_vertices.len() as i32,
+            count: 2,
+            indexed: false,
+            order: self.get_next_draw_order(),
+            batch_state: self.get_batch_state(None)
         };
-        let v2 = ImmediateRenderVertex {
-            position: Point4::new(v2.x, v2.y, v2.z, 1.0),
-            colour: *c2
+        self.push_vertex(ImmediateRenderVertex::new(v0, c0));
+        self.push_vertex(ImmediateRenderVertex::new(v1, c1));
+        self.current_line_draws.push(draw);
+    }
+
+    // vertex colours are multiplied by the texture colour
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_textured_triangle(&mut self, texture: gl_utils::gl_types::Texture, 
+        v0: &Point3<f32>, uv0: &Point2<f32>, c0: &Point4<f32>, 
+        v1: &Point3<f32>, uv1: &Point2<f32>, c1: &Point4<f32>, 
+        v2: &Point3<f32>, uv2: &Point2<f32>, c2: &Point4<f32>) {
+        debug_assert!(self.vertex_format.has_uv(), "textured triangle added to an ImmediateRender without uvs in its vertex format");
+        let draw = ImmediateRenderDrawcall {
+            primitive_type: glow::TRIANGLES,
+            start: self.current_vertices.len() as i32,
+            count: 3,
+            indexed: false,
+            order: self.get_next_draw_order(),
+            batch_state: self.get_batch_state(Some(texture))
         };
+        self.push_vertex(ImmediateRenderVertex::new_textured(v0, uv0, c0));
+        self.push_vertex(ImmediateRenderVertex::new_textured(v1, uv1, c1));
+        self.push_vertex(ImmediateRenderVertex::new_textured(v2, uv2, c2));
+        self.current_triangle_draws.push(draw);
+    }
+
+    // corners should be in winding order, the quad is split along v0 -> v2
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_textured_quad(&mut self, texture: gl_utils::gl_types::Texture, 
+        v0: &Point3<f32>, uv0: &Point2<f32>, 
+        v1: &Point3<f32>, uv1: &Point2<f32>, 
+        v2: &Point3<f32>, uv2: &Point2<f32>, 
+        v3: &Point3<f32>, uv3: &Point2<f32>, colour: &Point4<f32>) {
+        debug_assert!(self.vertex_format.has_uv(), "textured quad added to an ImmediateRender without uvs in its vertex format");
+        let base_vertex = self.current_vertices.len() as u32;
         let draw = ImmediateRenderDrawcall {
-            start_vertex: self.current_vertices.len() as i32,
-            vertex_count: 3
+            primitive_type: glow::TRIANGLES,
+            start: self.current_indices.len() as i32,
+            count: 6,
+            indexed: true,
+            order: self.get_next_draw_order(),
+            batch_state: self.get_batch_state(Some(texture))
         };
-        self.current_vertices.push(v0);
-        self.current_vertices.push(v1);
-        self.current_vertices.push(v2);
+        self.push_vertex(ImmediateRenderVertex::new_textured(v0, uv0, colour));
+        self.push_vertex(ImmediateRenderVertex::new_textured(v1, uv1, colour));
+        self.push_vertex(ImmediateRenderVertex::new_textured(v2, uv2, colour));
+        self.push_vertex(ImmediateRenderVertex::new_textured(v3, uv3, colour));
+        self.current_indices.extend([0, 1, 2, 0, 2, 3].iter().map(|index| base_vertex + index));
         self.current_triangle_draws.push(draw);
     }
 
-    pub fn add_line(&mut self, v0: &Point3<f32>, c0: &Point4<f32>, v1: &Point3<f32>, c1: &Point4<f32>) {
-        let v0 = ImmediateRenderVertex {
-            position: Point4::new(v0.x, v0.y, v0.z, 1.0),
-            colour: *c0
+    // size is in pixels, regardless of distance from the camera
+    pub fn add_point(&mut self, position: &Point3<f32>, size: f32, colour: &Point4<f32>) {
+        debug_assert!(self.vertex_format.has_point_size(), "point added to an ImmediateRender without point sizes in its vertex format");
+        let mut batch_state = self.get_batch_state(None);
+        batch_state.round_points = self.round_points;
+        let draw = ImmediateRenderDrawcall {
+            primitive_type: glow::POINTS,
+            start: self.current_vertices.len() as i32,
+            count: 1,
+            indexed: false,
+            order: self.get_next_draw_order(),
+            batch_state
         };
-        let v1 = ImmediateRenderVertex {
-            position: Point4::new(v1.x, v1.y, v1.z, 1.0),
-            colour: *c1
+        self.push_vertex(ImmediateRenderVertex::new_point(position, size, colour));
+        self.current_point_draws.push(draw);
+    }
+
+    // the texture text_render draws glyphs from, text is skipped until one is set
+    // the caller owns it, so one font texture can be shared by every ImmediateRender that draws text
+    pub fn set_font_texture(&mut self, font_texture: Option<gl_utils::gl_types::Texture>) {
+        self.font_texture = font_texture;
+    }
+
+    pub fn get_font_texture(&self) -> Option<gl_utils::gl_types::Texture> {
+        self.font_texture
+    }
+
+    // points added after this are drawn as circles instead of squares
+    pub fn set_round_points(&mut self, enabled: bool) {
+        self.round_points = enabled;
+    }
+
+    // width is in pixels, regardless of distance from the camera
+    // uses the same render state as other geometry, anti-aliased edges need alpha blending enabled
+    pub fn add_thick_line(&mut self, v0: &Point3<f32>, v1: &Point3<f32>, width: f32, colour: &Point4<f32>) {
+        let draw = ImmediateRenderDrawcall {
+            primitive_type: glow::TRIANGLES,
+            start: self.current_thick_line_vertices.len() as i32,
+            count: 6,
+            indexed: false,
+            order: self.get_next_draw_order(),
+            batch_state: self.get_batch_state(None)
+        };
+        let v0 = self.get_transform().transform_point(v0);
+        let v1 = self.get_transform().transform_point(v1);
+        let start = Point4::new(v0.x, v0.y, v0.z, 1.0);
+        let end = Point4::new(v1.x, v1.y, v1.z, 1.0);
+        let make_vertex = |side: f32, at_end: bool| ImmediateRenderThickLineVertex {
+            position: if at_end { end } else { start },
+            other_position: if at_end { start } else { end },
+            colour: *colour,
+            params: Point4::new(side, width, self.thick_line_feather, if at_end { 1.0 } else { 0.0 })
+        };
+        let quad = [make_vertex(-1.0, false), make_vertex(1.0, false), make_vertex(1.0, true), make_vertex(-1.0, true)];
+        self.current_thick_line_vertices.extend_from_slice(&[quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]);
+        self.current_thick_line_draws.push(draw);
+    }
+
+    // thick lines added after this will fade out over ~1 pixel at their edges
+    pub fn set_thick_line_antialiasing(&mut self, enabled: bool) {
+        self.thick_line_feather = if enabled { 1.0 } else { 0.0 };
+    }
+
+    // render state applies to all add_* calls that follow, and is kept across clear()
+    pub fn set_render_state(&mut self, render_state: RenderState) {
+        self.current_render_state = render_state;
+    }
+
+    pub fn get_render_state(&self) -> RenderState {
+        self.current_render_state
+    }
+
+    // draws are only ever added until the next clear, so the total so far is a unique, increasing order
+    fn get_next_draw_order(&self) -> u32 {
+        (self.current_triangle_draws.len() + self.current_line_draws.len() + self.current_point_draws.len() + self.current_thick_line_draws.len()) as u32
+    }
+
+    fn get_batch_state(&self, texture: Option<gl_utils::gl_types::Texture>) -> ImmediateRenderBatchState {
+        ImmediateRenderBatchState {
+            render_state: self.current_render_state,
+            texture,
+            round_points: false,
+            clip_rect: self.clip_rect_stack.last().copied()
+        }
+    }
+
+    // adds a single vertex without drawing anything, returns its index for use with the add_indexed_* fns
+    // indices are only valid until the next clear()
+    pub fn add_vertex(&mut self, position: &Point3<f32>, colour: &Point4<f32>) -> u32 {
+        self.push_vertex(ImmediateRenderVertex::new(position, colour));
+        (self.current_vertices.len() - 1) as u32
+    }
+
+    pub fn add_indexed_triangle(&mut self, i0: u32, i1: u32, i2: u32) {
+        let draw = ImmediateRenderDrawcall {
+            primitive_type: glow::TRIANGLES,
+            start: self.current_indices.len() as i32,
+            count: 3,
+            indexed: true,
+            order: self.get_next_draw_order(),
+            batch_state: self.get_batch_state(None)
         };
+        self.current_indices.extend_from_slice(&[i0, i1, i2]);
+        self.current_triangle_draws.push(draw);
+    }
+
+    pub fn add_indexed_line(&mut self, i0: u32, i1: u32) {
         let draw = ImmediateRenderDrawcall {
-            start_vertex: self.current_vertices.len() as i32,
-            vertex_count: 2
+            primitive_type: glow::LINES,
+            start: self.current_indices.len() as i32,
+            count: 2,
+            indexed: true,
+            order: self.get_next_draw_order(),
+            batch_state: self.get_batch_state(None)
         };
-        self.current_vertices.push(v0);
-        self.current_vertices.push(v1);
+        self.current_indices.extend_from_slice(&[i0, i1]);
         self.current_line_draws.push(draw);
     }
 
+    // add an indexed triangle list, indices are relative to the start of the vertices slice
+    pub fn add_indexed_mesh(&mut self, vertices: &[ImmediateRenderVertex], indices: &[u32]) {
+        let base_vertex = self.current_vertices.len() as u32;
+        let draw = ImmediateRenderDrawcall {
+            primitive_type: glow::TRIANGLES,
+            start: self.current_indices.len() as i32,
+            count: indices.len() as i32,
+            indexed: true,
+            order: self.get_next_draw_order(),
+            batch_state: self.get_batch_state(None)
+        };
+        for vertex in vertices
+        {
+            self.push_vertex(*vertex);
+        }
+        self.current_indices.extend(indices.iter().map(|index| base_vertex + index));
+        self.current_triangle_draws.push(draw);
+    }
+
+    // total number of times the vertex or index buffers had to grow to fit a frame's geometry
+    pub fn get_buffer_grow_count(&self) -> u32 {
+        self.vertex_buffer.get_grow_count() + self.index_buffer.get_grow_count() + self.thick_line_vertex_buffer.get_grow_count()
+    }
+
     pub fn clear(&mut self) {
         self.current_vertices.clear();
+        self.current_indices.clear();
         self.current_triangle_draws.clear();
         self.current_line_draws.clear();
+        self.current_point_draws.clear();
+        self.current_thick_line_vertices.clear();
+        self.current_thick_line_draws.clear();
+        self.transform_stack.clear();
+        self.transform_stack.push(Matrix4::identity());
+        self.clip_rect_stack.clear();
+        self.frame_stats = RenderStats::default();
     }
 
-    fn draw_compacted(gl: &glow::Context, primitive_type: u32, draws: &Vec<ImmediateRenderDrawcall>)
+    fn apply_batch_state(&self, gl: &glow::Context, batch_state: &ImmediateRenderBatchState)
     {
-        let mut last_vertex_index = -1;
-        let mut current_vertex_count = 0;
-        for draw in draws
+        batch_state.render_state.apply(gl);
+        unsafe {
+            gl.active_texture(glow::TEXTURE0);
+            gl.bind_texture(glow::TEXTURE_2D, batch_state.texture.or(self.white_texture));
+
+            match batch_state.clip_rect {
+                Some(clip_rect) => {
+                    // gl scissor origin is the bottom left of the viewport, clip rects use the top left
+                    gl.enable(glow::SCISSOR_TEST);
+                    gl.scissor(clip_rect.min_x, 
+                        self.viewport_size.y as i32 - clip_rect.max_y, 
+                        clip_rect.max_x - clip_rect.min_x, 
+                        clip_rect.max_y - clip_rect.min_y);
+                },
+                None => gl.disable(glow::SCISSOR_TEST)
+            }
+        }
+    }
+
+    fn submit_draw(&self, gl: &glow::Context, draw: &ImmediateRenderDrawcall, applied_state: &mut Option<ImmediateRenderBatchState>, stats: &mut RenderStats)
+    {
+        if *applied_state != Some(draw.batch_state)
         {
-            if draw.start_vertex == (last_vertex_index + current_vertex_count)
+            self.apply_batch_state(gl, &draw.batch_state);
+
+            // point style is a uniform on the main shader, only touch it when that shader is drawing points
+            if draw.primitive_type == glow::POINTS
             {
-                current_vertex_count += draw.vertex_count;
+                unsafe {
+                    let round_points_uniform_pos = gl.get_uniform_location(self.shader_program.unwrap(), "round_points");
+                    gl.uniform_1_i32(round_points_uniform_pos.as_ref(), draw.batch_state.round_points as i32);
+                }
             }
-            else 
+            *applied_state = Some(draw.batch_state);
+        }
+        unsafe {
+            if draw.indexed
             {
-                if current_vertex_count > 0
-                {
-                    unsafe {
-                        gl.draw_arrays(primitive_type, last_vertex_index, current_vertex_count);
+                gl.draw_elements(draw.primitive_type, draw.count, glow::UNSIGNED_INT, draw.start * size_of::<u32>() as i32);
+            }
+            else
+            {
+                gl.draw_arrays(draw.primitive_type, draw.start, draw.count);
+            }
+        }
+        stats.add_draw_call(draw.primitive_type, draw.count, 1);
+    }
+
+    // merges draws that are contiguous in the vertex/index buffer and share the same state into a single call
+    fn draw_compacted(&self, gl: &glow::Context, draws: &Vec<ImmediateRenderDrawcall>, applied_state: &mut Option<ImmediateRenderBatchState>, stats: &mut RenderStats)
+    {
+        stats.draws_submitted += draws.len() as u32;
+        let mut current_batch: Option<ImmediateRenderDrawcall> = None;
+        for draw in draws
+        {
+            match current_batch.as_mut() {
+                Some(batch) if batch.can_merge(draw) => batch.count += draw.count,
+                _ => {
+                    if let Some(batch) = current_batch
+                    {
+                        self.submit_draw(gl, &batch, applied_state, stats);
                     }
+                    current_batch = Some(*draw);
                 }
-                last_vertex_index = draw.start_vertex;
-                current_vertex_count = draw.vertex_count;
             }
         }
-        if last_vertex_index != -1 && current_vertex_count > 0
+        if let Some(batch) = current_batch
         {
-            unsafe {
-                gl.draw_arrays(primitive_type, last_vertex_index, current_vertex_count);
+            self.submit_draw(gl, &batch, applied_state, stats);
+        }
+    }
+
+    // average position of the vertices used by a draw
+    fn get_draw_center(&self, draw: &ImmediateRenderDrawcall) -> Point3<f32> {
+        let range = draw.start as usize..(draw.start + draw.count) as usize;
+        let mut position_sum = Point4::new(0.0, 0.0, 0.0, 0.0);
+        for i in range
+        {
+            let vertex_index = if draw.indexed { self.current_indices[i] as usize } else { i };
+            position_sum += self.current_vertices[vertex_index].position.coords;
+        }
+        Point3::new(position_sum.x, position_sum.y, position_sum.z) / draw.count as f32
+    }
+
+    // opaque draws keep their submission order, blended draws are sorted furthest from the camera first
+    // sorted draws are rewritten as indices appended to current_indices, so neighbours with the same state still merge
+    fn build_depth_sorted_draws(&mut self, draws: &[ImmediateRenderDrawcall], view: &Isometry3<f32>) -> Vec<ImmediateRenderDrawcall> {
+        let mut sorted_draws = Vec::new();
+        let mut blended_draws = Vec::new();
+        for draw in draws
+        {
+            if draw.batch_state.render_state.blend_mode == BlendMode::Opaque
+            {
+                sorted_draws.push(*draw);
+            }
+            else
+            {
+                let view_depth = (view * self.get_draw_center(draw)).z;      // camera looks down -z, so further away is more negative
+                blended_draws.push((view_depth, *draw));
             }
         }
+        blended_draws.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        for (_, draw) in blended_draws
+        {
+            let start = self.current_indices.len() as i32;
+            let range = draw.start as u32..(draw.start + draw.count) as u32;
+            if draw.indexed
+            {
+                self.current_indices.extend_from_within(range.start as usize..range.end as usize);
+            }
+            else
+            {
+                self.current_indices.extend(range);
+            }
+            sorted_draws.push(ImmediateRenderDrawcall { start, indexed: true, ..draw });
+        }
+        sorted_draws
     }
 
-    pub fn draw(&self, gl : &glow::Context, camera: &crate::render::camera::Camera) {
-        unsafe {
-             // copy vertex data to buffer
-            gl.bind_buffer(glow::ARRAY_BUFFER, self.vertex_buffer);
-            gl.buffer_sub_data_u8_slice(glow::ARRAY_BUFFER, 0, self.current_vertices.align_to::<u8>().1);
+    // when enabled, blended triangles/lines/points are drawn back to front after all opaque geometry
+    pub fn set_depth_sorting(&mut self, enabled: bool) {
+        self.depth_sorting = enabled;
+    }
+
+    // webgl has no polygon mode, so wireframe is done by drawing the edges of each triangle as lines
+    // edge indices are appended to indices, the returned line draws keep the state of the triangles they came from
+    fn build_wireframe_draws(triangle_draws: &[ImmediateRenderDrawcall], indices: &mut Vec<u32>) -> Vec<ImmediateRenderDrawcall> {
+        let mut line_draws = Vec::new();
+        for draw in triangle_draws
+        {
+            let start = indices.len() as i32;
+            for triangle in (draw.start..draw.start + draw.count).step_by(3)
+            {
+                let corners = if draw.indexed {
+                    [indices[triangle as usize], indices[triangle as usize + 1], indices[triangle as usize + 2]]
+                } else {
+                    [triangle as u32, triangle as u32 + 1, triangle as u32 + 2]
+                };
+                indices.extend_from_slice(&[corners[0], corners[1], corners[1], corners[2], corners[2], corners[0]]);
+            }
+            line_draws.push(ImmediateRenderDrawcall {
+                primitive_type: glow::LINES,
+                start,
+                count: indices.len() as i32 - start,
+                indexed: true,
+                ..*draw
+            });
+        }
+        line_draws
+    }
+
+    // debug view, triangles are drawn as their edges, thick lines are drawn as normal
+    // applies to baked meshes drawn with this immediate render too
+    pub fn set_wireframe(&mut self, enabled: bool) {
+        self.wireframe = enabled;
+    }
+
+    pub fn get_wireframe(&self) -> bool {
+        self.wireframe
+    }
 
+    // counters for everything drawn since the last clear(), including baked meshes
+    pub fn get_render_stats(&self) -> RenderStats {
+        self.frame_stats
+    }
+
+    // vertex data in the layout the vao expects, compact vertices are packed into packed_vertices first
+    fn get_vertex_bytes<'a>(vertex_format: ImmediateRenderVertexFormat, vertices: &'a [ImmediateRenderVertex], packed_vertices: &'a mut Vec<u8>) -> &'a [u8] {
+        match vertex_format {
+            ImmediateRenderVertexFormat::Full => unsafe { vertices.align_to::<u8>().1 },
+            _ => {
+                packed_vertices.clear();
+                for vertex in vertices
+                {
+                    vertex_format.pack_vertex(vertex, packed_vertices);
+                }
+                packed_vertices
+            }
+        }
+    }
+
+    fn bind_main_shader(&self, gl : &glow::Context, view_projection: &Matrix4<f32>) {
+        unsafe {
             gl.use_program(self.shader_program);
             let view_proj_uniform_pos = gl.get_uniform_location(self.shader_program.unwrap(), "view_projection_matrix");
-            gl.uniform_matrix_4_f32_slice(view_proj_uniform_pos.as_ref(), false, camera.get_view_projection_matrix().as_slice());
-            gl.bind_vertex_array(self.vertex_array);
+            gl.uniform_matrix_4_f32_slice(view_proj_uniform_pos.as_ref(), false, view_projection.as_slice());
+            let texture_uniform_pos = gl.get_uniform_location(self.shader_program.unwrap(), "diffuse_texture");
+            gl.uniform_1_i32(texture_uniform_pos.as_ref(), 0);
+            let round_points_uniform_pos = gl.get_uniform_location(self.shader_program.unwrap(), "round_points");
+            gl.uniform_1_i32(round_points_uniform_pos.as_ref(), 0);
         }
+    }
+
+    fn bind_thick_line_shader(&self, gl : &glow::Context, view_projection: &Matrix4<f32>) {
+        unsafe {
+            gl.use_program(self.thick_line_shader_program);
+            let view_proj_uniform_pos = gl.get_uniform_location(self.thick_line_shader_program.unwrap(), "view_projection_matrix");
+            gl.uniform_matrix_4_f32_slice(view_proj_uniform_pos.as_ref(), false, view_projection.as_slice());
 
-        Self::draw_compacted(gl, glow::TRIANGLES, &self.current_triangle_draws);
-        Self::draw_compacted(gl, glow::LINES, &self.current_line_draws);
+            // line widths are in pixels so we need the viewport size
+            let viewport_uniform_pos = gl.get_uniform_location(self.thick_line_shader_program.unwrap(), "viewport_size");
+            gl.uniform_2_f32(viewport_uniform_pos.as_ref(), self.viewport_size.x, self.viewport_size.y);
+        }
     }
-}
 
+    // leave gl in the default state for whoever draws next
+    fn reset_gl_state(&self, gl : &glow::Context) {
+        RenderState::default().apply(gl);
+        unsafe {
+            gl.bind_texture(glow::TEXTURE_2D, None);
+            gl.disable(glow::SCISSOR_TEST);
+        }
+    }
+
+    // splits draws into runs that use the same shader, in submission order
+    // the bool is true for runs of thick lines
+    fn build_ordered_passes(draws: &[ImmediateRenderDrawcall], thick_line_draws: &[ImmediateRenderDrawcall]) -> Vec<(bool, Vec<ImmediateRenderDrawcall>)> {
+        let mut tagged_draws: Vec<(bool, ImmediateRenderDrawcall)> = draws.iter().map(|draw| (false, *draw))
+            .chain(thick_line_draws.iter().map(|draw| (true, *draw)))
+            .collect();
+        tagged_draws.sort_by_key(|(_, draw)| draw.order);
+
+        let mut passes: Vec<(bool, Vec<ImmediateRenderDrawcall>)> = Vec::new();
+        for (is_thick_line, draw) in tagged_draws
+        {
+            match passes.last_mut() {
+                Some((pass_is_thick_line, pass_draws)) if *pass_is_thick_line == is_thick_line => pass_draws.push(draw),
+                _ => passes.push((is_thick_line, vec![draw]))
+            }
+        }
+        passes
+    }
+
+    // by default triangles, lines and points are each drawn together, then thick lines last
+    // ordered drawing replays everything in the order it was added instead, only merging neighbouring draws of the same type
+    // use it when depth testing is off (2d overlays) and layering matters, it replaces depth sorting
+    pub fn set_ordered_drawing(&mut self, enabled: bool) {
+        self.ordered_drawing = enabled;
+    }
+
+    // viewport_size should match the gl viewport, which is assumed to start at 0,0
+    // it is passed in rather than queried, glGet stalls the pipeline on some drivers (and always on webgl)
+    pub fn draw(&mut self, gl : &glow::Context, camera: &crate::render::camera::Camera, viewport_size: &Vector2<f32>) {
+        self.viewport_size = *viewport_size;
+        // sorting + wireframe append indices, remember where the real ones end so we can remove them again
+        let index_count = self.current_indices.len();
+        let mut draws = if self.wireframe {
+            Self::build_wireframe_draws(&self.current_triangle_draws, &mut self.current_indices)
+        } else {
+            self.current_triangle_draws.clone()
+        };
+        draws.extend(self.current_line_draws.iter().chain(self.current_point_draws.iter()));
+        let passes = if self.ordered_drawing {
+            Self::build_ordered_passes(&draws, &self.current_thick_line_draws)
+        } else {
+            if self.depth_sorting
+            {
+                draws = self.build_depth_sorted_draws(&draws, &camera.get_view_transform());
+            }
+            vec![(false, draws), (true, self.current_thick_line_draws.clone())]
+        };
+        let view_projection = camera.get_view_projection_matrix();
+
+        let mut stats = RenderStats::default();
+        let grow_count = self.get_buffer_grow_count();
+        let vertex_bytes = Self::get_vertex_bytes(self.vertex_format, &self.current_vertices, &mut self.packed_vertices);
+        stats.vertices_uploaded += self.current_vertices.len() as u32;
+        stats.bytes_uploaded += vertex_bytes.len();
+        self.vertex_buffer.upload(gl, vertex_bytes);
+        unsafe {
+            // element buffer is bound via the vao, vertex + index buffers move through their rings together
+            gl.bind_vertex_array(self.vertex_arrays[self.vertex_buffer.get_current_index()]);
+            let index_bytes = self.current_indices.align_to::<u8>().1;
+            stats.bytes_uploaded += index_bytes.len();
+            self.index_buffer.upload(gl, index_bytes);
+
+            if !self.current_thick_line_draws.is_empty()
+            {
+                let thick_line_bytes = self.current_thick_line_vertices.align_to::<u8>().1;
+                stats.vertices_uploaded += self.current_thick_line_vertices.len() as u32;
+                stats.bytes_uploaded += thick_line_bytes.len();
+                self.thick_line_vertex_buffer.upload(gl, thick_line_bytes);
+            }
+        }
+        stats.buffer_grows += self.get_buffer_grow_count() - grow_count;
+
+        for (is_thick_line, pass_draws) in &passes
+        {
+            if pass_draws.is_empty()
+            {
+                continue;
+            }
+            if *is_thick_line
+            {
+                unsafe {
+                    gl.bind_vertex_array(self.thick_line_vertex_arrays[self.thick_line_vertex_buffer.get_current_index()]);
+                }
+                self.bind_thick_line_shader(gl, &view_projection);
+            }
+            else
+            {
+                unsafe {
+                    gl.bind_vertex_array(self.vertex_arrays[self.vertex_buffer.get_current_index()]);
+                }
+                self.bind_main_shader(gl, &view_projection);
+            }
+
+            // binding the main shader resets round_points, so re-apply the state of the first batch
+            let mut applied_state = None;
+            self.draw_compacted(gl, pass_draws, &mut applied_state, &mut stats);
+        }
+
+        self.reset_gl_state(gl);
+        self.frame_stats += stats;
+        self.current_indices.truncate(index_count);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,128 @@
+// Geometry baked from an ImmediateRender into its own gl buffers
+// use it for geometry that never changes, it is drawn each frame without being re-added
+
+use glow::HasContext;
+use nalgebra::{Matrix4, Vector2};
+use crate::gl_utils;
+use crate::render::camera::Camera;
+use crate::render::render_stats::RenderStats;
+use super::{ImmediateRender, ImmediateRenderDrawcall, ImmediateRenderThickLineVertex, THICK_LINE_ATTRIBUTES};
+
+pub struct ImmediateRenderMesh
+{
+    vertex_array: Option<gl_utils::gl_types::VertexArray>,
+    vertex_buffer: Option<gl_utils::gl_types::Buffer>,
+    index_buffer: Option<gl_utils::gl_types::Buffer>,
+    draws: Vec<ImmediateRenderDrawcall>,    // triangles, then lines, then points
+    wireframe_draws: Vec<ImmediateRenderDrawcall>,  // same again with triangles replaced by their edges
+    thick_line_vertex_array: Option<gl_utils::gl_types::VertexArray>,
+    thick_line_vertex_buffer: Option<gl_utils::gl_types::Buffer>,
+    thick_line_draws: Vec<ImmediateRenderDrawcall>
+}
+
+// buffers are written once so we can let the driver put them wherever is fastest to read
+fn create_static_buffer(gl : &glow::Context, target: u32, data: &[u8]) -> Option<gl_utils::gl_types::Buffer>
+{
+    unsafe {
+        let buffer = match gl.create_buffer() {
+            Ok(buffer) => Some(buffer),
+            Err(text) => {
+                console_log!("Failed to create buffer - {text}");
+                None
+            }
+        };
+        gl.bind_buffer(target, buffer);
+        gl.buffer_data_u8_slice(target, data, glow::STATIC_DRAW);
+        gl.bind_buffer(target, None);
+        buffer
+    }
+}
+
+impl ImmediateRenderMesh {
+    pub fn cleanup(&mut self, gl : &glow::Context)
+    {
+        unsafe {
+            gl.delete_vertex_array(self.vertex_array.unwrap());
+            gl.delete_buffer(self.vertex_buffer.unwrap());
+            gl.delete_buffer(self.index_buffer.unwrap());
+            if let Some(vertex_array) = self.thick_line_vertex_array
+            {
+                gl.delete_vertex_array(vertex_array);
+                gl.delete_buffer(self.thick_line_vertex_buffer.unwrap());
+            }
+        }
+        self.vertex_array = None;
+        self.vertex_buffer = None;
+        self.index_buffer = None;
+        self.thick_line_vertex_array = None;
+        self.thick_line_vertex_buffer = None;
+        self.draws.clear();
+        self.wireframe_draws.clear();
+        self.thick_line_draws.clear();
+    }
+}
+
+impl ImmediateRender {
+    // moves everything added since the last clear into a new mesh, then clears
+    // render states, textures and the transform stack are baked in as they were when the geometry was added
+    // the mesh shares this immediate render's shaders, so it must be drawn with the same immediate render
+    pub fn bake_mesh(&mut self, gl : &glow::Context) -> ImmediateRenderMesh {
+        // wireframe edges are baked too, the indices live after the triangle indices
+        let mut indices = self.current_indices.clone();
+        let mut wireframe_draws = Self::build_wireframe_draws(&self.current_triangle_draws, &mut indices);
+        wireframe_draws.extend(self.current_line_draws.iter().chain(self.current_point_draws.iter()));
+
+        let vertex_size = self.vertex_format.get_vertex_size();
+        let vertex_buffer = create_static_buffer(gl, glow::ARRAY_BUFFER, Self::get_vertex_bytes(self.vertex_format, &self.current_vertices, &mut self.packed_vertices));
+        let index_buffer = create_static_buffer(gl, glow::ELEMENT_ARRAY_BUFFER, unsafe { indices.align_to::<u8>().1 });
+        let vertex_array = Self::create_vertex_array(gl, self.shader_program, self.vertex_format.get_attributes(), vertex_size, vertex_buffer, index_buffer);
+        let draws = self.current_triangle_draws.iter().chain(self.current_line_draws.iter()).chain(self.current_point_draws.iter()).copied().collect();
+
+        let (thick_line_vertex_array, thick_line_vertex_buffer) = if self.current_thick_line_draws.is_empty() {
+            (None, None)
+        } else {
+            let buffer = create_static_buffer(gl, glow::ARRAY_BUFFER, unsafe { self.current_thick_line_vertices.align_to::<u8>().1 });
+            let vertex_array = Self::create_vertex_array(gl, self.thick_line_shader_program, &THICK_LINE_ATTRIBUTES, size_of::<ImmediateRenderThickLineVertex>(), buffer, None);
+            (vertex_array, buffer)
+        };
+
+        let mesh = ImmediateRenderMesh {
+            vertex_array,
+            vertex_buffer,
+            index_buffer,
+            draws,
+            wireframe_draws,
+            thick_line_vertex_array,
+            thick_line_vertex_buffer,
+            thick_line_draws: self.current_thick_line_draws.clone()
+        };
+        self.clear();
+        mesh
+    }
+
+    // draws a baked mesh immediately, the transform is applied on top of any transforms baked into the mesh
+    // blended geometry in the mesh is not depth sorted, bake it in the order it should be drawn
+    pub fn draw_mesh(&mut self, gl : &glow::Context, mesh: &ImmediateRenderMesh, camera: &Camera, transform: &Matrix4<f32>, viewport_size: &Vector2<f32>) {
+        self.viewport_size = *viewport_size;
+        let view_projection = camera.get_view_projection_matrix() * transform;
+        let mut applied_state = None;
+        let mut stats = RenderStats::default();
+        unsafe {
+            gl.bind_vertex_array(mesh.vertex_array);
+        }
+        self.bind_main_shader(gl, &view_projection);
+        self.draw_compacted(gl, if self.wireframe { &mesh.wireframe_draws } else { &mesh.draws }, &mut applied_state, &mut stats);
+
+        if !mesh.thick_line_draws.is_empty()
+        {
+            unsafe {
+                gl.bind_vertex_array(mesh.thick_line_vertex_array);
+            }
+            self.bind_thick_line_shader(gl, &view_projection);
+            self.draw_compacted(gl, &mesh.thick_line_draws, &mut applied_state, &mut stats);
+        }
+
+        self.reset_gl_state(gl);
+        self.frame_stats += stats;
+    }
+}
\ No newline at end of file
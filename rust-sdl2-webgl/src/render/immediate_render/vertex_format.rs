@@ -0,0 +1,107 @@
+// Layout of ImmediateRenderVertex data as uploaded to the gl vertex buffer
+
+use super::ImmediateRenderVertex;
+
+// the compact formats only store what they need, so pick one with uvs / point sizes if the geometry has them
+// uvs default to 0,0 without a uv attribute, point sizes default to 0 (invisible)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ImmediateRenderVertexFormat
+{
+    Full,               // 40 bytes, everything stored as f32, vertices are uploaded as-is
+    Compact,            // 16 bytes, position + colour as rgba8, packed before upload
+    CompactTextured,    // 24 bytes, compact + uv as 2 x f32
+    CompactPoints       // 20 bytes, compact + point size as f32
+}
+
+// one shader input, in the terms glVertexAttribPointer wants
+pub struct VertexAttribute
+{
+    pub name: &'static str,
+    pub components: i32,
+    pub data_type: u32,
+    pub normalized: bool,
+    pub offset: i32
+}
+
+// point size aliases position.w in the full format
+const FULL_ATTRIBUTES: [VertexAttribute; 4] = [
+    VertexAttribute { name: "vs_in_position", components: 3, data_type: glow::FLOAT, normalized: false, offset: 0 },
+    VertexAttribute { name: "vs_in_point_size", components: 1, data_type: glow::FLOAT, normalized: false, offset: 12 },
+    VertexAttribute { name: "vs_in_colour", components: 4, data_type: glow::FLOAT, normalized: false, offset: 16 },
+    VertexAttribute { name: "vs_in_uv", components: 2, data_type: glow::FLOAT, normalized: false, offset: 32 }
+];
+
+const COMPACT_ATTRIBUTES: [VertexAttribute; 2] = [
+    VertexAttribute { name: "vs_in_position", components: 3, data_type: glow::FLOAT, normalized: false, offset: 0 },
+    VertexAttribute { name: "vs_in_colour", components: 4, data_type: glow::UNSIGNED_BYTE, normalized: true, offset: 12 }
+];
+
+const COMPACT_TEXTURED_ATTRIBUTES: [VertexAttribute; 3] = [
+    VertexAttribute { name: "vs_in_position", components: 3, data_type: glow::FLOAT, normalized: false, offset: 0 },
+    VertexAttribute { name: "vs_in_colour", components: 4, data_type: glow::UNSIGNED_BYTE, normalized: true, offset: 12 },
+    VertexAttribute { name: "vs_in_uv", components: 2, data_type: glow::FLOAT, normalized: false, offset: 16 }
+];
+
+const COMPACT_POINTS_ATTRIBUTES: [VertexAttribute; 3] = [
+    VertexAttribute { name: "vs_in_position", components: 3, data_type: glow::FLOAT, normalized: false, offset: 0 },
+    VertexAttribute { name: "vs_in_colour", components: 4, data_type: glow::UNSIGNED_BYTE, normalized: true, offset: 12 },
+    VertexAttribute { name: "vs_in_point_size", components: 1, data_type: glow::FLOAT, normalized: false, offset: 16 }
+];
+
+impl ImmediateRenderVertexFormat {
+    pub fn get_vertex_size(&self) -> usize
+    {
+        match self {
+            ImmediateRenderVertexFormat::Full => size_of::<ImmediateRenderVertex>(),
+            ImmediateRenderVertexFormat::Compact => 16,
+            ImmediateRenderVertexFormat::CompactTextured => 24,
+            ImmediateRenderVertexFormat::CompactPoints => 20
+        }
+    }
+
+    pub fn get_attributes(&self) -> &'static [VertexAttribute]
+    {
+        match self {
+            ImmediateRenderVertexFormat::Full => &FULL_ATTRIBUTES,
+            ImmediateRenderVertexFormat::Compact => &COMPACT_ATTRIBUTES,
+            ImmediateRenderVertexFormat::CompactTextured => &COMPACT_TEXTURED_ATTRIBUTES,
+            ImmediateRenderVertexFormat::CompactPoints => &COMPACT_POINTS_ATTRIBUTES
+        }
+    }
+
+    pub fn has_uv(&self) -> bool
+    {
+        matches!(self, ImmediateRenderVertexFormat::Full | ImmediateRenderVertexFormat::CompactTextured)
+    }
+
+    pub fn has_point_size(&self) -> bool
+    {
+        matches!(self, ImmediateRenderVertexFormat::Full | ImmediateRenderVertexFormat::CompactPoints)
+    }
+
+    // appends the packed representation of a vertex, must match the attributes of the format
+    // the full format is uploaded as-is and never packed
+    pub fn pack_vertex(&self, vertex: &ImmediateRenderVertex, packed_data: &mut Vec<u8>)
+    {
+        let to_unorm8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        packed_data.extend_from_slice(&vertex.position.x.to_le_bytes());
+        packed_data.extend_from_slice(&vertex.position.y.to_le_bytes());
+        packed_data.extend_from_slice(&vertex.position.z.to_le_bytes());
+        packed_data.extend_from_slice(&[
+            to_unorm8(vertex.colour.x), 
+            to_unorm8(vertex.colour.y), 
+            to_unorm8(vertex.colour.z), 
+            to_unorm8(vertex.colour.w)
+        ]);
+        match self {
+            ImmediateRenderVertexFormat::CompactTextured => {
+                packed_data.extend_from_slice(&vertex.uv.x.to_le_bytes());
+                packed_data.extend_from_slice(&vertex.uv.y.to_le_bytes());
+            },
+            ImmediateRenderVertexFormat::CompactPoints => {
+                packed_data.extend_from_slice(&vertex.position.w.to_le_bytes());
+            },
+            _ => ()
+        }
+    }
+}
\ No newline at end of file
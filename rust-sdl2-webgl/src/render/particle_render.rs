@@ -0,0 +1,220 @@
+// Draws lots of camera-facing round particles with one instanced draw call
+// each particle is a single instance, the quad corners come from gl_VertexID so there is no per-vertex data
+// alpha blended particles can be sorted back to front before upload, additive blending doesn't need it
+
+use glow::HasContext;
+use nalgebra::{Point3, Point4};
+use crate::gl_utils;
+use crate::render::camera::Camera;
+use crate::render::dynamic_buffer::{BufferStreaming, DynamicBuffer};
+use crate::render::render_state::{BlendMode, RenderState};
+use crate::render::render_stats::RenderStats;
+
+#[repr(C)]      // uploaded as-is, the field layout must match the attribute offsets below
+#[derive(Clone, Copy)]
+struct ParticleInstance
+{
+    position: Point3<f32>,
+    size: f32,              // world space radius
+    colour: [u8; 4]         // rgba8, normalized in the shader
+}
+
+pub struct ParticleRender
+{
+    shader_program: Option<gl_utils::gl_types::ShaderProgram>,
+    vertex_arrays: Vec<Option<gl_utils::gl_types::VertexArray>>,   // one per buffer in the instance buffer ring
+    instance_buffer: DynamicBuffer,
+    current_instances: Vec<ParticleInstance>,
+    sorted_instances: Vec<(f32, ParticleInstance)>,    // scratch space for depth sorting, kept to avoid reallocating every frame
+    render_state: RenderState,
+    depth_sorting: bool,
+    frame_stats: RenderStats    // everything drawn since the last clear
+}
+
+impl ParticleRender {
+    pub fn cleanup(&mut self, gl : &glow::Context)
+    {
+        unsafe {
+            for vertex_array in &self.vertex_arrays
+            {
+                gl.delete_vertex_array(vertex_array.unwrap());
+            }
+            gl_utils::unload_shader_program(gl, &self.shader_program.unwrap());
+        }
+        self.instance_buffer.cleanup(gl);
+        self.vertex_arrays.clear();
+        self.shader_program = None;
+    }
+
+    // the instance buffer grows if more than initial_particle_count particles are added
+    pub fn new(gl : &glow::Context, streaming: BufferStreaming, initial_particle_count: u32) -> Self
+    {
+        let vertex_shader_src = r#"#version 300 es
+            uniform mat4 view_matrix;
+            uniform mat4 projection_matrix;
+            layout (location = 0) in vec4 vs_in_position_size;
+            layout (location = 1) in vec4 vs_in_colour;
+            out vec4 vs_out_colour;
+            out vec2 vs_out_corner;
+            const vec2 corners[6] = vec2[6](vec2(-1.0, -1.0), vec2(1.0, -1.0), vec2(1.0, 1.0), vec2(-1.0, -1.0), vec2(1.0, 1.0), vec2(-1.0, 1.0));
+            void main() {
+                // expand the quad in view space so it always faces the camera
+                vec2 corner = corners[gl_VertexID];
+                vec4 view_position = view_matrix * vec4(vs_in_position_size.xyz, 1.0);
+                view_position.xy += corner * vs_in_position_size.w;
+                gl_Position = projection_matrix * view_position;
+                vs_out_colour = vs_in_colour;
+                vs_out_corner = corner;
+            }
+        "#;
+        let fragment_shader_src = r#"#version 300 es
+            precision highp float;
+            in vec4 vs_out_colour;
+            in vec2 vs_out_corner;
+            out vec4 fs_out_colour;
+            void main() {
+                if (dot(vs_out_corner, vs_out_corner) > 1.0) {
+                    discard;
+                }
+                fs_out_colour = vs_out_colour;
+            }
+        "#;
+        let shader_program = match gl_utils::load_shader_program(gl, vertex_shader_src, fragment_shader_src) {
+            Ok(shader_program) => Some(shader_program),
+            Err(text) => {
+                console_log!("Failed to load particle shaders - {text}");
+                None
+            }
+        };
+
+        let instance_size = size_of::<ParticleInstance>();
+        let instance_buffer = DynamicBuffer::new(gl, glow::ARRAY_BUFFER, instance_size * initial_particle_count as usize, streaming);
+        let mut vertex_arrays = Vec::new();
+        unsafe {
+            for index in 0..instance_buffer.get_buffer_count()
+            {
+                let vertex_array = match gl.create_vertex_array() {
+                    Ok(vertex_array) => Some(vertex_array),
+                    Err(text) => {
+                        console_log!("Failed to create vertex array - {text}");
+                        None
+                    }
+                };
+                gl.bind_vertex_array(vertex_array);
+                gl.bind_buffer(glow::ARRAY_BUFFER, instance_buffer.get_buffer_at(index));
+
+                // divisor of 1 = attributes advance once per instance instead of once per vertex
+                let position_size_location = gl.get_attrib_location(shader_program.unwrap(), "vs_in_position_size").unwrap();
+                gl.enable_vertex_attrib_array(position_size_location);
+                gl.vertex_attrib_pointer_f32(position_size_location, 4, glow::FLOAT, false, instance_size as i32, 0);
+                gl.vertex_attrib_divisor(position_size_location, 1);
+                let colour_location = gl.get_attrib_location(shader_program.unwrap(), "vs_in_colour").unwrap();
+                gl.enable_vertex_attrib_array(colour_location);
+                gl.vertex_attrib_pointer_f32(colour_location, 4, glow::UNSIGNED_BYTE, true, instance_size as i32, 16);
+                gl.vertex_attrib_divisor(colour_location, 1);
+
+                vertex_arrays.push(vertex_array);
+            }
+            gl.bind_vertex_array(None);
+            gl.bind_buffer(glow::ARRAY_BUFFER, None);
+        }
+
+        ParticleRender {
+            shader_program,
+            vertex_arrays,
+            instance_buffer,
+            current_instances: Vec::new(),
+            sorted_instances: Vec::new(),
+            render_state: RenderState::alpha_blended(),
+            depth_sorting: false,
+            frame_stats: RenderStats::default()
+        }
+    }
+
+    // blend/depth state used for all particles, alpha blended by default
+    pub fn set_render_state(&mut self, render_state: RenderState)
+    {
+        self.render_state = render_state;
+    }
+
+    // when enabled, alpha blended particles are drawn back to front
+    // costs a sort of every particle each frame, so it is off by default
+    pub fn set_depth_sorting(&mut self, enabled: bool)
+    {
+        self.depth_sorting = enabled;
+    }
+
+    // size is the world space radius, colour components are 0-1
+    pub fn add_particle(&mut self, position: &Point3<f32>, size: f32, colour: &Point4<f32>)
+    {
+        let to_u8 = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        self.current_instances.push(ParticleInstance {
+            position: *position,
+            size,
+            colour: [to_u8(colour.x), to_u8(colour.y), to_u8(colour.z), to_u8(colour.w)]
+        });
+    }
+
+    pub fn get_particle_count(&self) -> usize
+    {
+        self.current_instances.len()
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.current_instances.clear();
+        self.frame_stats = RenderStats::default();
+    }
+
+    fn sort_back_to_front(&mut self, camera: &Camera)
+    {
+        let view = camera.get_view_transform();
+        self.sorted_instances.clear();
+        self.sorted_instances.extend(self.current_instances.iter().map(|instance| ((view * instance.position).z, *instance)));
+        self.sorted_instances.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));     // camera looks down -z, so further away is more negative
+        self.current_instances.clear();
+        self.current_instances.extend(self.sorted_instances.iter().map(|(_, instance)| *instance));
+    }
+
+    // counters for everything drawn since the last clear()
+    pub fn get_render_stats(&self) -> RenderStats
+    {
+        self.frame_stats
+    }
+
+    pub fn draw(&mut self, gl : &glow::Context, camera: &Camera)
+    {
+        if self.current_instances.is_empty()
+        {
+            return;
+        }
+
+        if self.depth_sorting && self.render_state.blend_mode == BlendMode::Alpha
+        {
+            self.sort_back_to_front(camera);
+        }
+
+        self.render_state.apply(gl);
+        unsafe {
+            let instance_bytes = self.current_instances.align_to::<u8>().1;
+            self.frame_stats.vertices_uploaded += self.current_instances.len() as u32;
+            self.frame_stats.bytes_uploaded += instance_bytes.len();
+            let grow_count = self.instance_buffer.get_grow_count();
+            self.instance_buffer.upload(gl, instance_bytes);
+            self.frame_stats.buffer_grows += self.instance_buffer.get_grow_count() - grow_count;
+            gl.bind_vertex_array(self.vertex_arrays[self.instance_buffer.get_current_index()]);
+            gl.use_program(self.shader_program);
+            let view_uniform_pos = gl.get_uniform_location(self.shader_program.unwrap(), "view_matrix");
+            gl.uniform_matrix_4_f32_slice(view_uniform_pos.as_ref(), false, camera.get_view_transform().to_homogeneous().as_slice());
+            let projection_uniform_pos = gl.get_uniform_location(self.shader_program.unwrap(), "projection_matrix");
+            gl.uniform_matrix_4_f32_slice(projection_uniform_pos.as_ref(), false, camera.get_projection_matrix().as_slice());
+            gl.draw_arrays_instanced(glow::TRIANGLES, 0, 6, self.current_instances.len() as i32);
+            self.frame_stats.draws_submitted += 1;
+            self.frame_stats.add_draw_call(glow::TRIANGLES, 6, self.current_instances.len() as i32);
+            gl.bind_vertex_array(None);
+        }
+
+        // leave gl in the default state for whoever draws next
+        RenderState::default().apply(gl);
+    }
+}
\ No newline at end of file
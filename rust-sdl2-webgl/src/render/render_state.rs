@@ -0,0 +1,112 @@
+// Fixed-function gl state (blending, depth, culling) that can change between draw batches
+
+use glow::HasContext;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BlendMode
+{
+    Opaque,
+    Alpha,      // src * src_alpha + dst * (1 - src_alpha)
+    Additive    // src * src_alpha + dst
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CullMode
+{
+    None,
+    Back,
+    Front
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RenderState
+{
+    pub blend_mode: BlendMode,
+    pub depth_test: bool,
+    pub depth_write: bool,
+    pub cull_mode: CullMode
+}
+
+impl Default for RenderState {
+    // opaque geometry, depth tested + written, no culling
+    fn default() -> Self
+    {
+        RenderState {
+            blend_mode: BlendMode::Opaque,
+            depth_test: true,
+            depth_write: true,
+            cull_mode: CullMode::None
+        }
+    }
+}
+
+impl RenderState {
+    // translucent geometry should test against depth but not write to it
+    pub fn alpha_blended() -> Self
+    {
+        RenderState {
+            blend_mode: BlendMode::Alpha,
+            depth_write: false,
+            ..RenderState::default()
+        }
+    }
+
+    pub fn additive() -> Self
+    {
+        RenderState {
+            blend_mode: BlendMode::Additive,
+            depth_write: false,
+            ..RenderState::default()
+        }
+    }
+
+    // 2d overlays have no depth, draw order is everything
+    pub fn overlay_2d() -> Self
+    {
+        RenderState {
+            blend_mode: BlendMode::Alpha,
+            depth_test: false,
+            depth_write: false,
+            cull_mode: CullMode::None
+        }
+    }
+
+    pub fn apply(&self, gl: &glow::Context)
+    {
+        unsafe {
+            match self.blend_mode {
+                BlendMode::Opaque => gl.disable(glow::BLEND),
+                BlendMode::Alpha => {
+                    gl.enable(glow::BLEND);
+                    gl.blend_func(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
+                },
+                BlendMode::Additive => {
+                    gl.enable(glow::BLEND);
+                    gl.blend_func(glow::SRC_ALPHA, glow::ONE);
+                }
+            }
+
+            if self.depth_test
+            {
+                gl.enable(glow::DEPTH_TEST);
+            }
+            else
+            {
+                gl.disable(glow::DEPTH_TEST);
+            }
+            gl.depth_mask(self.depth_write);
+
+            match self.cull_mode {
+                CullMode::None => gl.disable(glow::CULL_FACE),
+                CullMode::Back => {
+                    gl.enable(glow::CULL_FACE);
+                    gl.cull_face(glow::BACK);
+                },
+                CullMode::Front => {
+                    gl.enable(glow::CULL_FACE);
+                    gl.cull_face(glow::FRONT);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+// Per-frame counters for what the renderers sent to the gpu
+
+use std::fmt;
+use std::ops::AddAssign;
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct RenderStats
+{
+    pub vertices_uploaded: u32,     // includes thick line vertices and particle instances
+    pub bytes_uploaded: usize,      // vertex + index + instance data
+    pub draws_submitted: u32,       // draws before compaction, roughly one per add_* call
+    pub draw_calls: u32,            // gl draw calls actually issued
+    pub triangles: u32,             // thick lines + particles count as 2 triangles each
+    pub lines: u32,
+    pub points: u32,
+    pub buffer_grows: u32           // gpu buffers reallocated to fit more data, should be 0 once geometry stops growing
+}
+
+impl RenderStats {
+    // counts one gl draw call of count vertices/indices
+    pub fn add_draw_call(&mut self, primitive_type: u32, count: i32, instance_count: i32)
+    {
+        let primitives = match primitive_type {
+            glow::TRIANGLES => count / 3,
+            glow::LINES => count / 2,
+            _ => count
+        } as u32 * instance_count as u32;
+        match primitive_type {
+            glow::TRIANGLES => self.triangles += primitives,
+            glow::LINES => self.lines += primitives,
+            glow::POINTS => self.points += primitives,
+            _ => {}
+        }
+        self.draw_calls += 1;
+    }
+}
+
+impl AddAssign for RenderStats {
+    fn add_assign(&mut self, other: Self)
+    {
+        self.vertices_uploaded += other.vertices_uploaded;
+        self.bytes_uploaded += other.bytes_uploaded;
+        self.draws_submitted += other.draws_submitted;
+        self.draw_calls += other.draw_calls;
+        self.triangles += other.triangles;
+        self.lines += other.lines;
+        self.points += other.points;
+        self.buffer_grows += other.buffer_grows;
+    }
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{} draw calls ({} submitted), {} tris, {} lines, {} points, {} verts / {}kb uploaded, {} buffer grows", 
+            self.draw_calls, self.draws_submitted, self.triangles, self.lines, self.points, self.vertices_uploaded, self.bytes_uploaded / 1024, self.buffer_grows)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,84 @@
+// Helpers to draw text with the built-in bitmap font
+// positions + sizes are in pixels with y pointing down, to match the 2d orthographic camera
+// text needs alpha blending, the glyph backgrounds are transparent
+
+use nalgebra::{Point2, Point3, Point4, Vector2};
+use crate::render::bitmap_font;
+use crate::render::camera::Camera;
+use crate::render::immediate_render::ImmediateRender;
+
+const LINE_SPACING: u32 = 2;    // extra pixels between lines, before scaling
+
+fn get_line_height(scale: f32) -> f32
+{
+    (bitmap_font::GLYPH_SIZE + LINE_SPACING) as f32 * scale
+}
+
+// size in pixels of the box the text would cover, '\n' starts a new line
+pub fn measure_text(text: &str, scale: f32) -> Vector2<f32>
+{
+    let longest_line = text.lines().map(|line| line.chars().count()).max().unwrap_or(0);
+    let line_count = text.lines().count();
+    if line_count == 0
+    {
+        return Vector2::zeros();
+    }
+    Vector2::new(
+        longest_line as f32 * bitmap_font::GLYPH_SIZE as f32 * scale,
+        line_count as f32 * get_line_height(scale) - LINE_SPACING as f32 * scale
+    )
+}
+
+// position is the top left of the first character, scale 1 = 8 pixel glyphs
+// integer scales keep the glyphs crisp
+pub fn draw_text(im_render: &mut ImmediateRender, position: &Point2<f32>, text: &str, scale: f32, colour: &Point4<f32>)
+{
+    let Some(font_texture) = im_render.get_font_texture() else {
+        return;
+    };
+    let glyph_size = bitmap_font::GLYPH_SIZE as f32 * scale;
+    let uv_size = Vector2::new(
+        bitmap_font::GLYPH_SIZE as f32 / bitmap_font::ATLAS_WIDTH as f32, 
+        bitmap_font::GLYPH_SIZE as f32 / bitmap_font::ATLAS_HEIGHT as f32);
+    for (line_index, line) in text.lines().enumerate()
+    {
+        let y = position.y + line_index as f32 * get_line_height(scale);
+        for (char_index, character) in line.chars().enumerate()
+        {
+            if character == ' '
+            {
+                continue;
+            }
+            let x = position.x + char_index as f32 * glyph_size;
+            let (atlas_x, atlas_y) = bitmap_font::get_glyph_atlas_position(character);
+            let uv_min = Point2::new(atlas_x as f32 / bitmap_font::ATLAS_WIDTH as f32, atlas_y as f32 / bitmap_font::ATLAS_HEIGHT as f32);
+            im_render.add_textured_quad(font_texture,
+                &Point3::new(x, y, 0.0), &uv_min,
+                &Point3::new(x + glyph_size, y, 0.0), &Point2::new(uv_min.x + uv_size.x, uv_min.y),
+                &Point3::new(x + glyph_size, y + glyph_size, 0.0), &(uv_min + uv_size),
+                &Point3::new(x, y + glyph_size, 0.0), &Point2::new(uv_min.x, uv_min.y + uv_size.y),
+                colour);
+        }
+    }
+}
+
+// label a 3d point, drawn into a 2d immediate render so it stays pixel-crisp at any distance
+// the text is centred on the projected point, world_camera is the camera the 3d scene is drawn with
+// returns false if the label was culled for being behind the camera, outside the near/far planes or off screen
+pub fn draw_world_text(im_render_2d: &mut ImmediateRender, world_camera: &Camera, viewport_size: &Vector2<f32>, 
+    world_position: &Point3<f32>, text: &str, scale: f32, colour: &Point4<f32>) -> bool
+{
+    let Some(screen_position) = world_camera.world_to_screen(world_position, viewport_size) else {
+        return false;
+    };
+    let text_size = measure_text(text, scale);
+    let top_left = Point2::from(screen_position - text_size * 0.5);
+    if top_left.x > viewport_size.x || top_left.y > viewport_size.y || top_left.x + text_size.x < 0.0 || top_left.y + text_size.y < 0.0
+    {
+        return false;
+    }
+
+    // snap to whole pixels so the glyphs don't blur
+    draw_text(im_render_2d, &top_left.map(|c| c.round()), text, scale, colour);
+    true
+}
\ No newline at end of file
@@ -41,6 +41,17 @@ pub fn create_sdl2_window_and_context(window_width: u32, window_height: u32) ->
     }
 }
 
+// map the sdl keys we care about to app keys
+fn get_app_key(scancode: sdl2::keyboard::Scancode) -> Option<crate::input::Key>
+{
+    match scancode {
+        sdl2::keyboard::Scancode::F1 => Some(crate::input::Key::F1),
+        sdl2::keyboard::Scancode::Q => Some(crate::input::Key::Q),
+        sdl2::keyboard::Scancode::E => Some(crate::input::Key::E),
+        _ => None
+    }
+}
+
 // sdl 2 event pump
 pub fn run_sdl2_event_loop(mut context: SDL2Context, mut app_state: crate::app::ApplicationState)
 {
@@ -49,6 +60,7 @@ pub fn run_sdl2_event_loop(mut context: SDL2Context, mut app_state: crate::app::
     let perf_timer_frequency = sdl_timer.performance_frequency();
     let mut perf_timer_last_count = sdl_timer.performance_counter();
     while running {
+        let mut wheel_delta = 0.0;
         for event in context.event_loop.poll_iter() {
             match event {
                 sdl2::event::Event::Quit { .. } => running = false,
@@ -59,6 +71,10 @@ pub fn run_sdl2_event_loop(mut context: SDL2Context, mut app_state: crate::app::
                         context.window_height = h as u32;
                     }
                 }
+                sdl2::event::Event::MouseWheel { precise_y, direction, .. } => {
+                    // some platforms report natural scrolling as flipped
+                    wheel_delta += if direction == sdl2::mouse::MouseWheelDirection::Flipped { -precise_y } else { precise_y };
+                }
                 _ => {}
             }
         }
@@ -71,7 +87,12 @@ pub fn run_sdl2_event_loop(mut context: SDL2Context, mut app_state: crate::app::
                 position_y: mouse_state.y(),
                 left_btn_down: mouse_state.left(),
                 middle_btn_down: mouse_state.middle(),
-                right_btn_down: mouse_state.right()
+                right_btn_down: mouse_state.right(),
+                wheel_delta,
+                touch_gesture: false
+            },
+            keyboard_state: crate::input::KeyboardState {
+                keys_down: context.event_loop.keyboard_state().pressed_scancodes().filter_map(get_app_key).collect()
             }
         };
 
@@ -80,7 +101,7 @@ pub fn run_sdl2_event_loop(mut context: SDL2Context, mut app_state: crate::app::
         perf_timer_last_count = perf_timer_this_count;
 
         crate::app::tick(&mut app_state, &app_input, tick_delta, context.window_width, context.window_height);
-        crate::app::draw_gl(&context.gl, &app_state, context.window_width, context.window_height);
+        crate::app::draw_gl(&context.gl, &mut app_state, context.window_width, context.window_height);
         
         context.window.gl_swap_window();
         
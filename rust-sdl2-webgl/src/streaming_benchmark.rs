@@ -0,0 +1,93 @@
+// Benchmark scene for the dynamic buffer streaming modes
+// draws a large animated triangle field every frame and cycles through the streaming modes,
+// logging the average frame time of each. Build with --features benchmark
+// note that vsync will cap the frame time, turn it off to see the difference
+
+use crate::render::camera::Camera;
+use crate::render::dynamic_buffer::BufferStreaming;
+use crate::render::immediate_render::{ImmediateRender, ImmediateRenderVertexFormat};
+use nalgebra::{Point3, Point4, Vector2};
+
+const GRID_CELLS: u32 = 160;            // 160 * 160 quads = 51200 triangles = 153600 vertices
+const GRID_CELL_SIZE: f32 = 0.5;
+const FRAMES_PER_MODE: u32 = 300;
+const WARMUP_FRAMES: u32 = 30;          // skip the first frames after a mode switch, the buffers are still growing
+
+pub struct StreamingBenchmark
+{
+    modes: Vec<(BufferStreaming, ImmediateRender)>,
+    current_mode: usize,
+    frame_count: u32,
+    total_frame_time: f64,
+    time: f64
+}
+
+impl StreamingBenchmark {
+    pub fn new(gl : &glow::Context) -> Self
+    {
+        let modes = [BufferStreaming::Single, BufferStreaming::Orphan, BufferStreaming::Ring(3)].iter().map(|streaming| {
+            (*streaming, ImmediateRender::new(gl, ImmediateRenderVertexFormat::Full, *streaming, 1024 * 32, 1024))
+        }).collect();
+        StreamingBenchmark {
+            modes,
+            current_mode: 0,
+            frame_count: 0,
+            total_frame_time: 0.0,
+            time: 0.0
+        }
+    }
+
+    pub fn cleanup(&mut self, gl : &glow::Context)
+    {
+        for (_, im_render) in &mut self.modes
+        {
+            im_render.cleanup(gl);
+        }
+    }
+
+    pub fn tick(&mut self, delta_time: f64)
+    {
+        self.time += delta_time;
+        self.frame_count += 1;
+        if self.frame_count > WARMUP_FRAMES
+        {
+            self.total_frame_time += delta_time;
+        }
+        if self.frame_count == WARMUP_FRAMES + FRAMES_PER_MODE
+        {
+            let average_ms = self.total_frame_time * 1000.0 / FRAMES_PER_MODE as f64;
+            console_log!("Streaming benchmark - {:?}: {:.3}ms average frame time", self.modes[self.current_mode].0, average_ms);
+            self.current_mode = (self.current_mode + 1) % self.modes.len();
+            self.frame_count = 0;
+            self.total_frame_time = 0.0;
+        }
+
+        // rebuild every vertex each frame so the whole buffer is uploaded
+        let im_render = &mut self.modes[self.current_mode].1;
+        im_render.clear();
+        let half_size = GRID_CELLS as f32 * GRID_CELL_SIZE * 0.5;
+        let height = |x: f32, z: f32| ((x * 0.3 + self.time as f32 * 2.0).sin() + (z * 0.2 + self.time as f32).cos()) * 0.5 + 1.0;
+        let colour = |y: f32| Point4::new(0.2, y * 0.4, 0.8, 1.0);
+        for cell_z in 0..GRID_CELLS
+        {
+            for cell_x in 0..GRID_CELLS
+            {
+                let x0 = cell_x as f32 * GRID_CELL_SIZE - half_size;
+                let z0 = cell_z as f32 * GRID_CELL_SIZE - half_size;
+                let x1 = x0 + GRID_CELL_SIZE;
+                let z1 = z0 + GRID_CELL_SIZE;
+                let p00 = Point3::new(x0, height(x0, z0), z0);
+                let p10 = Point3::new(x1, height(x1, z0), z0);
+                let p01 = Point3::new(x0, height(x0, z1), z1);
+                let p11 = Point3::new(x1, height(x1, z1), z1);
+                im_render.add_triangle(&p00, &colour(p00.y), &p01, &colour(p01.y), &p11, &colour(p11.y));
+                im_render.add_triangle(&p00, &colour(p00.y), &p11, &colour(p11.y), &p10, &colour(p10.y));
+            }
+        }
+    }
+
+    pub fn draw(&mut self, gl : &glow::Context, camera: &Camera, viewport_size: &Vector2<f32>)
+    {
+        self.modes[self.current_mode].1.draw(gl, camera, viewport_size);
+    }
+}
\ No newline at end of file
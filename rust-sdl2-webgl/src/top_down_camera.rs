@@ -1,14 +1,20 @@
-// A camera that hovers above the world and has a fixed look vector
+// A camera that hovers above the world, looking down at a fixed pitch
 // Interpolates between current position and target position
+// yaw orbits the camera around the point it is looking at on the ground
 
-use nalgebra::{Point3,Vector3};
+use nalgebra::{Point3,Vector3,Rotation3};
 
 pub struct TopDownCamera
 {
     pub current_position: Point3<f32>,
     pub look_direction: Vector3<f32>,
     pub move_speed_multi: f32,
-    target_position: Point3<f32>
+    pub min_height: f32,        // zoom limits
+    pub max_height: f32,
+    pub yaw_smoothing: f32,     // how quickly the yaw catches up with its target, higher = faster
+    target_position: Point3<f32>,
+    current_yaw: f32,           // radians around world y, 0 = the look direction passed to new()
+    target_yaw: f32
 }
 
 impl TopDownCamera {
@@ -18,7 +24,12 @@ impl TopDownCamera {
             current_position: position, 
             look_direction: look.normalize(), 
             target_position: position,
-            move_speed_multi: 2.0
+            move_speed_multi: 2.0,
+            min_height: 4.0,
+            max_height: 60.0,
+            yaw_smoothing: 8.0,
+            current_yaw: 0.0,
+            target_yaw: 0.0
         }
     }
 
@@ -33,6 +44,48 @@ impl TopDownCamera {
             let actual_speed = self.move_speed_multi * slowdown_factor;
             self.current_position = self.current_position + movement.normalize() * actual_speed * time_delta as f32;
         }
+
+        // ease towards the target yaw, then orbit by however much we turned this tick
+        // easing never quite gets there, so snap once close enough and stop orbiting
+        const YAW_SNAP_ANGLE: f32 = 1.0e-4;     // radians
+        let yaw_remaining = self.target_yaw - self.current_yaw;
+        if yaw_remaining != 0.0
+        {
+            let snap = yaw_remaining.abs() < YAW_SNAP_ANGLE;
+            let yaw_step = if snap { yaw_remaining } else { yaw_remaining * (1.0 - (-self.yaw_smoothing * time_delta as f32).exp()) };
+            self.current_yaw = if snap { self.target_yaw } else { self.current_yaw + yaw_step };
+            self.orbit(yaw_step);
+        }
+    }
+
+    // rotate the position, target and look direction around the ground focus point
+    // falls back to turning on the spot if we can't see the ground
+    fn orbit(&mut self, angle: f32)
+    {
+        let rotation = Rotation3::from_axis_angle(&Vector3::y_axis(), angle);
+        let pivot = if self.look_direction.y < 0.0 && self.current_position.y > 0.0
+        {
+            self.current_position + self.look_direction * (-self.current_position.y / self.look_direction.y)
+        }
+        else
+        {
+            self.current_position
+        };
+        self.current_position = pivot + rotation * (self.current_position - pivot);
+        self.target_position = pivot + rotation * (self.target_position - pivot);
+        self.look_direction = (rotation * self.look_direction).normalize();     // renormalize so errors don't build up
+    }
+
+    // turn by an angle in radians around world y, positive = anticlockwise looking down
+    pub fn rotate_yaw(&mut self, angle: f32)
+    {
+        self.target_yaw += angle;
+    }
+
+    // rotation from the unrotated camera's world axes to the current ones
+    pub fn get_yaw_rotation(&self) -> Rotation3<f32>
+    {
+        Rotation3::from_axis_angle(&Vector3::y_axis(), self.current_yaw)
     }
 
     pub fn set_target(&mut self, target: Point3<f32>)
@@ -40,13 +93,45 @@ impl TopDownCamera {
         self.target_position = target;
     }
 
+    pub fn get_target(&self) -> Point3<f32>
+    {
+        self.target_position
+    }
+
+    // move so the camera ends up looking at a point on the ground, keeping the target height
+    pub fn set_ground_focus_target(&mut self, ground_point: Point3<f32>)
+    {
+        if self.look_direction.y >= 0.0
+        {
+            console_log!("TopDownCamera::set_ground_focus_target - camera is not looking down");
+            return;
+        }
+        let distance = (ground_point.y - self.target_position.y) / self.look_direction.y;
+        self.target_position = ground_point - self.look_direction * distance;
+    }
+
+    // move the target along the look direction, positive = towards the ground
+    // the target height is kept between min_height and max_height
+    pub fn zoom(&mut self, distance: f32)
+    {
+        if self.look_direction.y > -0.01
+        {
+            console_log!("TopDownCamera::zoom - camera is not looking down");
+            return;
+        }
+        let new_height = (self.target_position.y + self.look_direction.y * distance).clamp(self.min_height, self.max_height);
+        let clamped_distance = (new_height - self.target_position.y) / self.look_direction.y;
+        self.target_position += self.look_direction * clamped_distance;
+    }
+
     pub fn apply_to_render_camera(&self, render_cam: &mut crate::render::camera::Camera)
     {
         // up direction does not need to be perfect, but it cannot converge on look direction
+        // when looking straight down use the yawed forward direction, so the view still turns with the yaw
         let mut up_direction: Vector3<f32> = Vector3::y();
         if self.look_direction.y >= -1.01 && self.look_direction.y <= -0.99    // pretty much looking straigt down
         {
-            up_direction = Vector3::z();
+            up_direction = self.get_yaw_rotation() * Vector3::z();
         }
         render_cam.look_at(
             self.current_position, 
@@ -5,9 +5,27 @@ use std::rc::Rc;
 // since input state is captured among multiple JS callbacks, we need mutable global state
 // use LazyLock to achieve this since it allows non-const initialisers
 
+use std::collections::HashMap;
 use std::sync::{LazyLock, Mutex};
 static INPUT_STATE: LazyLock<Mutex<crate::input::InputState>> = LazyLock::new( || Mutex::new(crate::input::InputState::default()));
 
+// active touch positions by pointer id, two touches = pinch zoom
+static TOUCH_POSITIONS: LazyLock<Mutex<HashMap<i32, (f32, f32)>>> = LazyLock::new( || Mutex::new(HashMap::new()));
+const PINCH_PIXELS_PER_WHEEL_NOTCH: f32 = 40.0;
+
+// distance between the two touches of a pinch
+fn get_pinch_distance(touches: &HashMap<i32, (f32, f32)>) -> Option<f32>
+{
+    if touches.len() != 2
+    {
+        return None;
+    }
+    let mut positions = touches.values();
+    let (x0, y0) = positions.next()?;
+    let (x1, y1) = positions.next()?;
+    Some(((x1 - x0) * (x1 - x0) + (y1 - y0) * (y1 - y0)).sqrt())
+}
+
 pub struct WasmContext
 {
     pub gl : glow::Context,
@@ -66,26 +84,74 @@ fn register_input_events(canvas: &web_sys::HtmlCanvasElement)
     canvas.set_onmouseup(Some(on_mouse_up.as_ref().unchecked_ref()));
     on_mouse_up.forget();
 
+    // wheel deltas come in pixels, lines or pages depending on the browser + device
+    let on_wheel = Closure::<dyn FnMut(web_sys::WheelEvent)>::new(move |event: web_sys::WheelEvent| {
+        event.prevent_default();    // don't scroll the page
+        let notches = match event.delta_mode() {
+            web_sys::WheelEvent::DOM_DELTA_PIXEL => event.delta_y() / 100.0,
+            web_sys::WheelEvent::DOM_DELTA_LINE => event.delta_y() / 3.0,
+            _ => event.delta_y()
+        };
+        INPUT_STATE.lock().unwrap().mouse_state.wheel_delta -= notches as f32;    // browser delta y is positive when scrolling towards the user
+    });
+    canvas.set_onwheel(Some(on_wheel.as_ref().unchecked_ref()));
+    on_wheel.forget();
+
     // basic pointer events to handle touch-devices
-    // pretend touch events are just mouse left-click events for now
+    // pretend a single touch is a mouse left-click, two touches pinch to zoom
+    // stop the browser handling touches itself, otherwise it pinch zooms the page and cancels our pointers
+    if let Err(error) = canvas.style().set_property("touch-action", "none")
+    {
+        console_log!("Failed to set canvas touch-action - {error:?}");
+    }
     let on_pointer_down = Closure::<dyn FnMut(web_sys::PointerEvent)>::new(move |event: web_sys::PointerEvent| {
         let mut mutable_input = INPUT_STATE.lock().unwrap();
         if event.pointer_type() == "touch"
         {
             console_log!("Touch!");
+            let mut touches = TOUCH_POSITIONS.lock().unwrap();
+            touches.insert(event.pointer_id(), (event.client_x() as f32, event.client_y() as f32));
             mutable_input.mouse_state.position_x = event.client_x();
             mutable_input.mouse_state.position_y = event.client_y();
-            mutable_input.mouse_state.left_btn_down = true;
+            mutable_input.mouse_state.left_btn_down = touches.len() == 1;    // a second finger starts a pinch instead
+            if touches.len() > 1
+            {
+                mutable_input.mouse_state.touch_gesture = true;     // lasts until every finger is lifted
+            }
         }
     });
     canvas.set_onpointerdown(Some(on_pointer_down.as_ref().unchecked_ref()));
     on_pointer_down.forget();
 
+    let on_pointer_move = Closure::<dyn FnMut(web_sys::PointerEvent)>::new(move |event: web_sys::PointerEvent| {
+        if event.pointer_type() == "touch"
+        {
+            let mut touches = TOUCH_POSITIONS.lock().unwrap();
+            if !touches.contains_key(&event.pointer_id())
+            {
+                return;
+            }
+            let new_position = (event.client_x() as f32, event.client_y() as f32);
+            let old_distance = get_pinch_distance(&touches);
+            touches.insert(event.pointer_id(), new_position);
+            if let (Some(old_distance), Some(new_distance)) = (old_distance, get_pinch_distance(&touches))
+            {
+                // fingers moving apart zooms in
+                INPUT_STATE.lock().unwrap().mouse_state.wheel_delta += (new_distance - old_distance) / PINCH_PIXELS_PER_WHEEL_NOTCH;
+            }
+        }
+    });
+    canvas.set_onpointermove(Some(on_pointer_move.as_ref().unchecked_ref()));
+    on_pointer_move.forget();
+
     let on_pointer_up = Closure::<dyn FnMut(web_sys::PointerEvent)>::new(move |event: web_sys::PointerEvent| {
         let mut mutable_input = INPUT_STATE.lock().unwrap();
         if event.pointer_type() == "touch"
         {
             console_log!("Touch release!");
+            let mut touches = TOUCH_POSITIONS.lock().unwrap();
+            touches.remove(&event.pointer_id());
+            mutable_input.mouse_state.touch_gesture &= !touches.is_empty();
             mutable_input.mouse_state.position_x = event.client_x();
             mutable_input.mouse_state.position_y = event.client_y();
             mutable_input.mouse_state.left_btn_down = false;
@@ -99,6 +165,9 @@ fn register_input_events(canvas: &web_sys::HtmlCanvasElement)
         if event.pointer_type() == "touch"
         {
             console_log!("Touch cancel!");
+            let mut touches = TOUCH_POSITIONS.lock().unwrap();
+            touches.remove(&event.pointer_id());
+            mutable_input.mouse_state.touch_gesture &= !touches.is_empty();
             mutable_input.mouse_state.left_btn_down = false;
         }
     });
@@ -113,6 +182,40 @@ fn register_input_events(canvas: &web_sys::HtmlCanvasElement)
     on_context_menu.forget();
 }
 
+// map the browser key codes we care about to app keys
+fn get_app_key(code: &str) -> Option<crate::input::Key>
+{
+    match code {
+        "F1" => Some(crate::input::Key::F1),
+        "KeyQ" => Some(crate::input::Key::Q),
+        "KeyE" => Some(crate::input::Key::E),
+        _ => None
+    }
+}
+
+// the canvas only gets key events when focused, so listen on the window instead
+fn register_keyboard_events()
+{
+    let on_key_down = Closure::<dyn FnMut(web_sys::KeyboardEvent)>::new(move |event: web_sys::KeyboardEvent| {
+        if let Some(key) = get_app_key(&event.code())
+        {
+            event.prevent_default();    // stop the browser using keys we handle (F1 = help)
+            INPUT_STATE.lock().unwrap().keyboard_state.keys_down.insert(key);
+        }
+    });
+    window().set_onkeydown(Some(on_key_down.as_ref().unchecked_ref()));
+    on_key_down.forget();
+
+    let on_key_up = Closure::<dyn FnMut(web_sys::KeyboardEvent)>::new(move |event: web_sys::KeyboardEvent| {
+        if let Some(key) = get_app_key(&event.code())
+        {
+            INPUT_STATE.lock().unwrap().keyboard_state.keys_down.remove(&key);
+        }
+    });
+    window().set_onkeyup(Some(on_key_up.as_ref().unchecked_ref()));
+    on_key_up.forget();
+}
+
 // Get the gl context from the canvas
 pub fn create_context() -> WasmContext
 {
@@ -133,6 +236,7 @@ pub fn create_context() -> WasmContext
     let gl = glow::Context::from_webgl2_context(webgl2_context);
 
     register_input_events(&canvas);
+    register_keyboard_events();
 
     WasmContext { gl: gl, last_tick_time: 0.0 }
 }
@@ -170,6 +274,7 @@ pub fn wasm_main_loop(mut wasm_context : WasmContext, mut app_state: crate::app:
 
         crate::app::tick(&mut app_state, &INPUT_STATE.lock().unwrap(), tick_delta_ms / 1000.0, canvas.width(), canvas.height()); 
         crate::app::draw_gl(&wasm_context.gl, &mut app_state, canvas.width(), canvas.height());    // call the shared render fn
+        INPUT_STATE.lock().unwrap().mouse_state.wheel_delta = 0.0;     // wheel events accumulate between ticks
 
         request_animation_frame(f.borrow().as_ref().unwrap());  // register next frame
     }) as Box<dyn FnMut(f64)>));